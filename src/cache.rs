@@ -0,0 +1,185 @@
+use crate::{config::Config, sources::Sources, BoxResult};
+use camino::Utf8PathBuf;
+use std::{collections::BTreeMap, ffi::OsString, path::Path, process::ExitStatus, sync::Mutex};
+
+/// The files a command reads, used to decide whether it is up to date.
+pub struct Inputs {
+    /// Extensions of the source files which are inputs, e.g. `rs`.
+    pub extensions: &'static [&'static str],
+    /// Names of other files which are inputs wherever they occur, e.g. `rustfmt.toml`.
+    pub files: &'static [&'static str],
+    /// Whether the `CMake` context file is an input, for the paths of the tools it names.
+    pub cmake_context: bool,
+}
+
+impl Inputs {
+    /// The Rust sources of the workspace together with the configuration files which affect how they are checked.
+    pub const RUST: Self = Self {
+        extensions: &["rs"],
+        files: &[
+            "Cargo.toml",
+            "Cargo.lock",
+            "rust-toolchain.toml",
+            "rustfmt.toml",
+            ".rustfmt.toml",
+            "clippy.toml",
+        ],
+        cmake_context: false,
+    };
+
+    /// The C++ sources of the workspace together with the configuration files which affect how they are checked.
+    pub const CXX: Self = Self {
+        extensions: &crate::sources::CXX_EXTENSIONS,
+        files: &["CMakeLists.txt", ".clang-format", ".clang-tidy"],
+        cmake_context: true,
+    };
+
+    fn contains(&self, path: &Path) -> bool {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        self.files.contains(&name) || self.extensions.contains(&extension)
+    }
+}
+
+/// Serializes updates of the fingerprint file by concurrently running commands.
+static STORE: Mutex<()> = Mutex::new(());
+
+/// A hash of everything that determines the outcome of a command: its arguments, the rust toolchain channel and the
+/// contents of its [`Inputs`].
+///
+/// Fingerprints of successful runs are stored in `target/xtask/fingerprints.json`, keyed on the command line. The hash
+/// is the 64-bit FNV-1a hash of those, so that it is the same across builds of xtask and platforms.
+pub struct Fingerprint {
+    key: String,
+    inputs: &'static Inputs,
+    hash: String,
+}
+
+impl Fingerprint {
+    /// Computes the fingerprint of running the xtask command line `args` (without the leading `xtask`).
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an input file cannot be read.
+    pub fn compute(config: &Config, inputs: &'static Inputs, args: &[OsString]) -> BoxResult<Self> {
+        let key = args
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut fingerprint = Self {
+            key,
+            inputs,
+            hash: String::new(),
+        };
+        fingerprint.hash = fingerprint.hash(config)?;
+        Ok(fingerprint)
+    }
+
+    /// The command line the fingerprint was computed for.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns whether the command's inputs are unchanged since its last successful run.
+    #[must_use]
+    pub fn is_fresh(&self, config: &Config) -> bool {
+        let _guard = STORE.lock();
+        load(config).get(&self.key) == Some(&self.hash)
+    }
+
+    /// Records a successful run of the command, fingerprinting its inputs as they are now.
+    ///
+    /// The inputs are hashed anew since the command may have modified them, e.g. `xtask fmt`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an input file cannot be read or the fingerprint file cannot be written.
+    pub fn save(&self, config: &Config) -> BoxResult<()> {
+        let hash = self.hash(config)?;
+        let _guard = STORE.lock();
+        let mut fingerprints = load(config);
+        fingerprints.insert(self.key.clone(), hash);
+        let path = path(config);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&fingerprints)?;
+        std::fs::write(&path, json + "\n").map_err(|err| format!("failed to write `{path}`: {err}"))?;
+        Ok(())
+    }
+
+    /// Saves the fingerprint if `status` is that of a successful run, printing a warning if that fails.
+    pub fn update(&self, config: &Config, status: Option<ExitStatus>) {
+        if status.map_or(true, |status| status.success()) {
+            if let Err(err) = self.save(config) {
+                crate::process::eprintln(&format!("warning: failed to cache `xtask {}`: {err}", self.key));
+            }
+        }
+    }
+
+    fn hash(&self, config: &Config) -> BoxResult<String> {
+        let mut hasher = Fnv1a::new();
+        hasher.write_field(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.write_field(self.key.as_bytes());
+        if let Ok(rust_toolchain) = config.rust_toolchain(&self.key) {
+            hasher.write_field(rust_toolchain.toolchain.channel.as_bytes());
+        }
+        if self.inputs.cmake_context {
+            // The file is read rather than loaded with `Config::cmake_context`, which would keep a stale context from
+            // being regenerated before the command runs.
+            hasher.write_field(&std::fs::read(config.cmake_context_path()).unwrap_or_default());
+        }
+        let sources = Sources::new(config);
+        for (path, _) in sources.files(|path| self.inputs.contains(path))? {
+            let relative = path.strip_prefix(sources.workspace_root()).unwrap_or(&path);
+            hasher.write_field(relative.to_string_lossy().as_bytes());
+            hasher.write_field(&std::fs::read(&path)?);
+        }
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+}
+
+/// The 64-bit FNV-1a hash, whose value is fixed unlike that of [`std::collections::hash_map::DefaultHasher`].
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Hashes `bytes` preceded by their length, so that consecutive fields cannot run into each other.
+    fn write_field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn path(config: &Config) -> Utf8PathBuf {
+    config.cargo_metadata.target_directory.join("xtask/fingerprints.json")
+}
+
+/// Loads the stored fingerprints, treating a missing or unreadable file as empty.
+fn load(config: &Config) -> BTreeMap<String, String> {
+    std::fs::read_to_string(path(config))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
@@ -1,39 +1,61 @@
 mod build;
 mod check;
+mod ci;
 pub mod clang;
 mod clippy;
 mod cmake;
+mod completions;
 mod doc;
 mod fmt;
 mod miri;
 mod tarpaulin;
 mod test;
+#[cfg(test)]
+mod tests;
 mod udeps;
 mod valgrind;
+mod watch;
 
 pub use self::{
-    build::build,
-    check::check,
-    clang::clang,
-    clippy::clippy,
-    cmake::cmake,
-    doc::doc,
-    fmt::fmt,
-    miri::miri,
-    tarpaulin::tarpaulin,
-    test::test,
-    udeps::udeps,
-    valgrind::valgrind,
+    build::Build,
+    check::Check,
+    ci::Ci,
+    clang::Clang,
+    clippy::Clippy,
+    cmake::CMake,
+    completions::Completions,
+    doc::Doc,
+    fmt::Fmt,
+    miri::Miri,
+    tarpaulin::Tarpaulin,
+    test::Test,
+    udeps::Udeps,
+    valgrind::Valgrind,
+    watch::Watch,
 };
 
-use crate::config::Config;
-use camino::Utf8PathBuf;
-use std::ffi::OsString;
+use crate::{
+    cache::Inputs,
+    config::{CMakeContext, Config},
+    executor::Invocation,
+    options::Options,
+    registry::Registry,
+    runner::{ProcessRunner, Runner},
+    spec::Spec,
+    BoxResult,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::{ffi::OsString, process::ExitStatus};
 
 pub struct Context<'a> {
     pub config: &'a Config,
+    pub registry: Option<&'a Registry>,
+    pub options: Options,
     pub args: &'a mut pico_args::Arguments,
     pub tool_args: Vec<OsString>,
+    /// Runs the processes spawned by the command.
+    pub runner: &'a dyn Runner,
+    /// The directory to run tools in, if not the workspace root (see [`Context::current_dir`]).
     pub current_dir: Option<Utf8PathBuf>,
     pub subcommand: Option<String>,
 }
@@ -42,10 +64,79 @@ impl<'a> Context<'a> {
     pub fn new(config: &'a Config, args: &'a mut pico_args::Arguments, tool_args: Vec<OsString>) -> Context<'a> {
         Context {
             config,
+            registry: None,
+            options: Options::default(),
             args,
             tool_args,
+            runner: &ProcessRunner,
             current_dir: None,
             subcommand: None,
         }
     }
+
+    /// Returns the directory to run tools in: the `current_dir` field if set, otherwise the root of the loaded
+    /// workspace.
+    #[must_use]
+    pub fn current_dir(&self) -> &Utf8Path {
+        self.current_dir
+            .as_deref()
+            .unwrap_or(&self.config.cargo_metadata.workspace_root)
+    }
+
+    /// Returns the `CMake` context for the xtask command named `command`, configuring the `CMake` build first if the
+    /// context is missing or stale and `--auto-configure` is given or the user agrees.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Configuring the `CMake` build fails
+    /// - The `CMake` context cannot be loaded, as for [`Config::cmake_context`]
+    pub fn cmake_context(&self, command: &str) -> BoxResult<&'a CMakeContext> {
+        crate::configure::cmake_context(self, command)
+    }
+}
+
+/// A command which can be registered with a [`Registry`](crate::registry::Registry) and invoked as `xtask <name>`.
+pub trait Task: Send + Sync {
+    /// The command line interface of the command, from which its name, help text and argument parsing are derived.
+    fn spec(&self) -> &'static Spec;
+
+    /// Returns the commands which must succeed before this command runs with `args`.
+    ///
+    /// `args` are the arguments following the command name, without any tool arguments, and have been checked against
+    /// the command's [`Spec`] before the prerequisites run. Prerequisites shared by several commands run only once per
+    /// xtask invocation.
+    fn dependencies(&self, _args: &[OsString]) -> Vec<Invocation> {
+        vec![]
+    }
+
+    /// Returns the files this command reads when run with `args`, if its outcome depends on nothing else.
+    ///
+    /// When the cache is enabled, a command with inputs is skipped if neither its arguments nor its inputs have
+    /// changed since its last successful run. `args` are as for [`Task::dependencies`].
+    fn inputs(&self, _args: &[OsString]) -> Option<&'static Inputs> {
+        None
+    }
+
+    /// Runs the command without loading a workspace, if it does not operate on one, so that it also works outside of a
+    /// Cargo workspace, e.g. `xtask completions`.
+    ///
+    /// Returns `None` for commands which operate on a workspace, which are run with [`Task::run`] once its [`Config`]
+    /// is loaded instead.
+    fn run_standalone(
+        &self,
+        _registry: &Registry,
+        _args: &mut pico_args::Arguments,
+        _tool_args: &[OsString],
+    ) -> Option<BoxResult<Option<ExitStatus>>> {
+        None
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>>;
 }
@@ -0,0 +1,110 @@
+use crate::{
+    command::{Context, Task},
+    error::XtaskError,
+    executor::{Executor, Invocation, Outcome, Report},
+    options::Verbosity,
+    spec::{Flag, Spec},
+    BoxResult,
+};
+use std::process::ExitStatus;
+
+static SPEC: Spec = Spec::new("ci", "Run the configured CI pipeline of xtask commands").flags(&[
+    Flag::new("--keep-going", "Continue with the remaining steps after a step fails"),
+    Flag::new("--fail-fast", "Stop at the first step which fails"),
+]);
+
+pub struct Ci;
+
+impl Task for Ci {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - The command is not run through a [`Registry`](crate::registry::Registry)
+    /// - The prerequisites of the steps form a cycle
+    /// - Any of the steps fails
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
+
+        if !context.tool_args.is_empty() {
+            return Err(XtaskError::bad_arguments("`xtask ci` does not accept extra tool arguments").into());
+        }
+
+        let registry = context
+            .registry
+            .ok_or("`xtask ci` must be dispatched through a `Registry`")?;
+        let ci = &context.config.xtask.ci;
+        let keep_going = match (matches.contains("--keep-going"), matches.contains("--fail-fast")) {
+            (true, true) => {
+                let message = "`--keep-going` and `--fail-fast` cannot be used together";
+                return Err(XtaskError::bad_arguments(message).into());
+            },
+            (true, false) => true,
+            (false, true) => false,
+            (false, false) => ci.keep_going,
+        };
+
+        let invocations = ci.steps.iter().map(|step| Invocation::parse(step)).collect();
+        let reports = Executor::new(registry, context.config, &context.options).run(invocations, keep_going)?;
+
+        if Verbosity::current() > Verbosity::Quiet {
+            summary(&reports);
+        }
+
+        let failures = reports.iter().filter(|report| report.outcome.failed()).count();
+        if failures > 0 {
+            let total = reports.len();
+            return Err(format!("{failures} of {total} `xtask ci` steps failed").into());
+        }
+
+        Ok(None)
+    }
+}
+
+fn summary(reports: &[Report]) {
+    let rows = reports
+        .iter()
+        .map(|report| {
+            let (result, status) = match &report.outcome {
+                Outcome::Finished {
+                    status: Some(status), ..
+                } if !status.success() => ("FAILED", status.to_string()),
+                Outcome::Finished {
+                    status: Some(status), ..
+                } => ("ok", status.to_string()),
+                Outcome::Finished { status: None, .. } => ("ok", String::from("-")),
+                Outcome::Errored { .. } => ("FAILED", String::from("error")),
+                Outcome::Cached => ("cached", String::from("-")),
+                Outcome::Skipped => ("skipped", String::from("-")),
+            };
+            let elapsed = report
+                .outcome
+                .elapsed()
+                .map_or_else(|| String::from("-"), |elapsed| format!("{:.2}s", elapsed.as_secs_f64()));
+            (&report.label, result, status, elapsed)
+        })
+        .collect::<Vec<_>>();
+    let width = |column: fn(&(&String, &str, String, String)) -> usize, header: &str| {
+        rows.iter()
+            .map(column)
+            .chain(std::iter::once(header.len()))
+            .max()
+            .unwrap_or_default()
+    };
+    let step_width = width(|row| row.0.len(), "STEP");
+    let status_width = width(|row| row.2.len(), "STATUS");
+    println!();
+    println!(
+        "{:<step_width$}  {:<7}  {:<status_width$}  DURATION",
+        "STEP", "RESULT", "STATUS"
+    );
+    for (label, result, status, elapsed) in rows {
+        println!("{label:<step_width$}  {result:<7}  {status:<status_width$}  {elapsed}");
+    }
+}
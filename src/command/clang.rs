@@ -1,88 +1,101 @@
-use crate::{command::Context, BoxResult};
+use crate::{
+    cache::Inputs,
+    command::{Context, Task},
+    error::XtaskError,
+    executor::Invocation,
+    spec::{Spec, Subcommand},
+    BoxResult,
+};
 use std::{
     ffi::OsString,
     process::{Command, ExitStatus},
 };
 
-pub fn help() -> &'static str {
-    let help = r#"
-    xtask-clang
-
-    USAGE:
-    xtask clang [SUBCOMMAND]
+static SPEC: Spec = Spec::new("clang", "Run clang tools on the project's C++ code")
+    .subcommands(&[
+        Subcommand::new(
+            "format",
+            "Run run-clang-format.py on the project's C++ code\nUse `-- --help` to see the usage for run-clang-format.py",
+        ),
+        Subcommand::new(
+            "tidy",
+            "Run run-clang-tidy      on the project's C++ code\nUse `-- --help` to see the usage for run-clang-tidy",
+        ),
+    ])
+    .tool("clang subcommand");
 
-    FLAGS:
-    -h, --help          Prints help information
-    -- '...'            Extra arguments to pass to the clang subcommand
+pub struct Clang;
 
-    SUBCOMMANDS:
-        format          Run run-clang-format.py on the project's C++ code
-                        Use `-- --help` to see the usage for run-clang-format.py
-        tidy            Run run-clang-tidy      on the project's C++ code
-                        Use `-- --help` to see the usage for run-clang-tidy
-    "#
-    .trim();
-    help
-}
+impl Task for Clang {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
+    }
 
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn clang(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = help();
+    fn dependencies(&self, args: &[OsString]) -> Vec<Invocation> {
+        if subcommand(args).map_or(false, |subcommand| subcommand == "tidy") {
+            vec![Invocation::parse("check"), Invocation::parse("cmake build")]
+        } else {
+            vec![]
+        }
+    }
 
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+    fn inputs(&self, args: &[OsString]) -> Option<&'static Inputs> {
+        subcommand(args)
+            .filter(|subcommand| *subcommand == "format")
+            .map(|_| &Inputs::CXX)
     }
 
-    let clang_subcommand: String = context.subcommand.ok_or("expected a subcommand for `xtask clang`")?;
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, mut context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
+
+        let clang_subcommand = context
+            .subcommand
+            .take()
+            .or(matches.subcommand)
+            .ok_or_else(|| XtaskError::bad_arguments("expected a subcommand for `xtask clang`"))?;
 
-    crate::handler::unused(context.args)?;
+        let status = match &*clang_subcommand {
+            "format" => {
+                let cmake_context = context.cmake_context("clang format")?;
+                let run_clang_format_tool = &cmake_context.bin_run_clang_format;
+                let mut cmd = Command::new(run_clang_format_tool);
+                cmd.current_dir(context.current_dir());
+                if !context.tool_args.contains(&OsString::from("--clang-format-executable")) {
+                    let clang_format_tool = cmake_context.bin_clang_format.as_str();
+                    cmd.args(["--clang-format-executable", clang_format_tool]);
+                }
+                cmd.args(context.tool_args);
+                context.runner.status(&context.options, &mut cmd)?
+            },
+            "tidy" => {
+                let cmake_context = context.cmake_context("clang tidy")?;
+                let run_clang_tidy_tool = &cmake_context.bin_run_clang_tidy;
+                let mut cmd = Command::new(run_clang_tidy_tool);
+                cmd.current_dir(context.current_dir());
+                if !context.tool_args.contains(&OsString::from("-clang-tidy-binary")) {
+                    let clang_tidy_tool = cmake_context.bin_clang_tidy.as_str();
+                    cmd.args(["-clang-tidy-binary", clang_tidy_tool]);
+                }
+                cmd.args(context.tool_args);
+                context.runner.status(&context.options, &mut cmd)?
+            },
+            _ => return Err(SPEC.unrecognized_subcommand(&clang_subcommand)),
+        };
 
-    let status = match &*clang_subcommand {
-        "format" => {
-            let run_clang_format_tool = &context.config.cmake_context.bin_run_clang_format;
-            let mut cmd = Command::new(run_clang_format_tool);
-            if !context.tool_args.contains(&OsString::from("--clang-format-executable")) {
-                let clang_format_tool = context.config.cmake_context.bin_clang_format.as_str();
-                cmd.args(["--clang-format-executable", clang_format_tool]);
-            }
-            cmd.args(context.tool_args);
-            cmd.status()?
-        },
-        "tidy" => {
-            {
-                let mut cmd = Command::new("cargo");
-                cmd.args(["check"]);
-                let status = cmd.status()?;
-                crate::handler::subcommand_result("cargo check", Ok(Some(status)));
-            }
-            // {
-            //     let config = context.config;
-            //     let mut args = pico_args::Arguments::from_vec(vec!["build".into()]);
-            //     let tool_args = vec![];
-            //     let context = Context::new(config, &mut args, tool_args);
-            //     let result = crate::command::cmake(context);
-            //     crate::handler::subcommand_result("cmake", result);
-            // }
-            let run_clang_tidy_tool = &context.config.cmake_context.bin_run_clang_tidy;
-            let mut cmd = Command::new(run_clang_tidy_tool);
-            if !context.tool_args.contains(&OsString::from("-clang-tidy-binary")) {
-                let clang_tidy_tool = context.config.cmake_context.bin_clang_tidy.as_str();
-                cmd.args(["-clang-tidy-binary", clang_tidy_tool]);
-            }
-            cmd.args(context.tool_args);
-            cmd.status()?
-        },
-        _ => {
-            println!("{help}\n");
-            return Err(format!("unrecognized `xtask clang` subcommand `{clang_subcommand}`").into());
-        },
-    };
+        Ok(status)
+    }
+}
 
-    Ok(Some(status))
+/// Returns the subcommand in the `xtask clang` arguments `args`, if any.
+fn subcommand(args: &[OsString]) -> Option<&OsString> {
+    args.iter().find(|arg| !arg.to_string_lossy().starts_with('-'))
 }
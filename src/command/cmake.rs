@@ -1,52 +1,68 @@
-use crate::{command::Context, BoxResult};
+use crate::{
+    command::{Context, Task},
+    spec::{Spec, Subcommand},
+    BoxResult,
+};
+use camino::{Utf8Path, Utf8PathBuf};
 use std::process::{Command, ExitStatus};
 
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn cmake(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-cmake
-
-USAGE:
-xtask cmake [SUBCOMMAND]
-
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cmake subcommand
-
-SUBCOMMANDS:
-    build
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+static SPEC: Spec = Spec::new("cmake", "Configure the project's CMake build")
+    .subcommands(&[Subcommand::new(
+        "build",
+        "Configure the project's CMake build directory",
+    )])
+    .tool("cmake command");
+
+pub struct CMake;
+
+impl Task for CMake {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
     }
 
-    let Some(cmake_subcommand) = context.args.opt_free_from_str::<String>()? else {
-        println!("{help}\n");
-        return Ok(None);
-    };
-
-    crate::handler::unused(context.args)?;
-
-    let status = if cmake_subcommand == "build" {
-        let mut cmd = Command::new("cmake");
-        cmd.args(["-G", "Ninja"]);
-        cmd.args(["-S", "."]);
-        cmd.args(["-B", "build"]);
-        cmd.args(context.tool_args);
-        cmd.current_dir(&context.config.cargo_metadata.workspace_root);
-        cmd.status()?
-    } else {
-        println!("{help}\n");
-        return Err(format!("unrecognized `xtask cmake` subcommand `{cmake_subcommand}`").into());
-    };
-
-    Ok(Some(status))
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
+
+        let Some(cmake_subcommand) = matches.subcommand else {
+            println!("{}\n", SPEC.help());
+            return Ok(None);
+        };
+
+        let status = match &*cmake_subcommand {
+            "build" => {
+                let mut cmd = Command::new("cmake");
+                cmd.current_dir(context.current_dir());
+                cmd.args(["-G", &context.config.xtask.cmake.generator]);
+                let config = context.config;
+                cmd.arg("-S")
+                    .arg(relative(&config.cmake_source_dir(), context.current_dir()));
+                cmd.arg("-B")
+                    .arg(relative(&config.cmake_build_dir(), context.current_dir()));
+                cmd.args(config.cmake_build_args());
+                cmd.args(context.tool_args);
+                context.runner.status(&context.options, &mut cmd)?
+            },
+            _ => unreachable!("unrecognized subcommands are rejected by `Spec::parse`"),
+        };
+
+        Ok(status)
+    }
+}
+
+/// Returns `path` relative to `base` if it is inside `base`, and `path` itself otherwise.
+fn relative(path: &Utf8Path, base: &Utf8Path) -> Utf8PathBuf {
+    match path.strip_prefix(base) {
+        Ok(relative) if relative.as_str().is_empty() => Utf8PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
 }
@@ -0,0 +1,73 @@
+use crate::{
+    command::{Context, Task},
+    completions::Shell,
+    error::XtaskError,
+    registry::Registry,
+    spec::{Flag, Spec, Subcommand},
+    BoxResult,
+};
+use std::{ffi::OsString, process::ExitStatus};
+
+static SPEC: Spec = Spec::new("completions", "Generate shell completion scripts for xtask")
+    .flags(&[Flag::new("--bin", "Name of the command to complete [default: xtask]").value("NAME")])
+    .subcommands(&[
+        Subcommand::new("bash", "Print the completion script for bash"),
+        Subcommand::new("elvish", "Print the completion script for elvish"),
+        Subcommand::new("fish", "Print the completion script for fish"),
+        Subcommand::new("zsh", "Print the completion script for zsh"),
+    ]);
+
+pub struct Completions;
+
+impl Task for Completions {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
+    }
+
+    fn run_standalone(
+        &self,
+        registry: &Registry,
+        args: &mut pico_args::Arguments,
+        tool_args: &[OsString],
+    ) -> Option<BoxResult<Option<ExitStatus>>> {
+        Some(complete(registry, args, tool_args))
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - The command is not run through a [`Registry`]
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let registry = context
+            .registry
+            .ok_or("`xtask completions` must be dispatched through a `Registry`")?;
+        complete(registry, context.args, &context.tool_args)
+    }
+}
+
+/// Prints the completion script for the shell named in `args`, for the commands in `registry`.
+fn complete(
+    registry: &Registry,
+    args: &mut pico_args::Arguments,
+    tool_args: &[OsString],
+) -> BoxResult<Option<ExitStatus>> {
+    let Some(matches) = SPEC.parse(args)? else {
+        return Ok(None);
+    };
+
+    let Some(shell) = &matches.subcommand else {
+        println!("{}\n", SPEC.help());
+        return Ok(None);
+    };
+
+    if !tool_args.is_empty() {
+        let message = "`xtask completions` does not accept extra tool arguments";
+        return Err(XtaskError::bad_arguments(message).into());
+    }
+
+    let bin = matches.value("--bin").unwrap_or("xtask");
+    print!("{}", shell.parse::<Shell>()?.generate(registry, bin));
+
+    Ok(None)
+}
@@ -1,39 +1,39 @@
-use crate::{command::Context, BoxResult};
+use crate::{
+    command::{Context, Task},
+    spec::Spec,
+    BoxResult,
+};
 use std::process::{Command, ExitStatus};
 
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn doc(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-doc
+static SPEC: Spec = Spec::new("doc", "Generate documentation for the project's Rust packages").tool("cargo command");
 
-USAGE:
-xtask doc
+pub struct Doc;
 
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cargo command
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+impl Task for Doc {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
     }
 
-    crate::handler::unused(context.args)?;
-
-    let toolchain = crate::config::rust::toolchain::nightly(context.config);
-
-    let mut cmd = Command::new("cargo");
-    cmd.current_dir(crate::workspace::project_root()?);
-    cmd.args([&format!("+{toolchain}"), "doc"]);
-    cmd.args(context.tool_args);
-    let status = cmd.status()?;
-
-    Ok(Some(status))
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        if SPEC.parse(context.args)?.is_none() {
+            return Ok(None);
+        }
+
+        let toolchain = crate::config::rust::toolchain::nightly(context.config, SPEC.name)?;
+
+        let mut cmd = Command::new("cargo");
+        cmd.current_dir(context.current_dir());
+        cmd.args([&format!("+{toolchain}"), "doc"]);
+        cmd.args(context.tool_args);
+        let status = context.runner.status(&context.options, &mut cmd)?;
+
+        Ok(status)
+    }
 }
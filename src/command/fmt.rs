@@ -1,39 +1,47 @@
-use crate::{command::Context, BoxResult};
-use std::process::{Command, ExitStatus};
-
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn fmt(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-format
-
-USAGE:
-xtask format
-
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cargo command
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+use crate::{
+    cache::Inputs,
+    command::{Context, Task},
+    spec::Spec,
+    BoxResult,
+};
+use std::{
+    ffi::OsString,
+    process::{Command, ExitStatus},
+};
+
+static SPEC: Spec = Spec::new("fmt", "Format the project's Rust code with rustfmt").tool("cargo command");
+
+pub struct Fmt;
+
+impl Task for Fmt {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
     }
 
-    crate::handler::unused(context.args)?;
-
-    let toolchain = crate::config::rust::toolchain::nightly(context.config);
-
-    let mut cmd = Command::new("cargo");
-    cmd.current_dir(crate::workspace::project_root()?);
-    cmd.args([&format!("+{toolchain}"), "fmt", "--all"]);
-    cmd.args(context.tool_args);
-    let status = cmd.status()?;
+    fn inputs(&self, _args: &[OsString]) -> Option<&'static Inputs> {
+        Some(&Inputs::RUST)
+    }
 
-    Ok(Some(status))
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        if SPEC.parse(context.args)?.is_none() {
+            return Ok(None);
+        }
+
+        let toolchain = crate::config::rust::toolchain::nightly(context.config, SPEC.name)?;
+
+        let mut cmd = Command::new("cargo");
+        cmd.current_dir(context.current_dir());
+        cmd.args([&format!("+{toolchain}"), "fmt", "--all"]);
+        cmd.args(context.tool_args);
+        let status = context.runner.status(&context.options, &mut cmd)?;
+
+        Ok(status)
+    }
 }
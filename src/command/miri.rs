@@ -1,56 +1,52 @@
-use crate::{command::Context, BoxResult};
+use crate::{
+    command::{Context, Task},
+    spec::{Spec, Subcommand},
+    BoxResult,
+};
 use std::process::{Command, ExitStatus};
 
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn miri(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-miri
-
-USAGE:
-xtask miri [SUBCOMMAND]
-
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cargo command
-
-SUBCOMMANDS:
-    test            Run the project's tests  with cargo-miri
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+static SPEC: Spec = Spec::new("miri", "Run the project's tests with cargo-miri")
+    .subcommands(&[Subcommand::new("test", "Run the project's tests with cargo-miri")])
+    .tool("cargo command");
+
+pub struct Miri;
+
+impl Task for Miri {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
     }
 
-    let Some(miri_subcommand) = context.args.opt_free_from_str::<String>()? else {
-        println!("{help}\n");
-        return Ok(None);
-    };
-
-    crate::handler::unused(context.args)?;
-
-    let toolchain = crate::config::rust::toolchain::nightly(context.config);
-
-    let status = match &*miri_subcommand {
-        "test" => {
-            let mut cmd = Command::new("cargo");
-            cmd.current_dir(crate::workspace::project_root()?);
-            cmd.args([&format!("+{toolchain}"), "miri"]);
-            cmd.args([miri_subcommand]);
-            cmd.args(context.tool_args);
-            cmd.status()?
-        },
-        _ => {
-            println!("{help}\n");
-            return Err(format!("unrecognized `xtask miri` subcommand `{miri_subcommand}`").into());
-        },
-    };
-
-    Ok(Some(status))
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
+
+        let Some(miri_subcommand) = matches.subcommand else {
+            println!("{}\n", SPEC.help());
+            return Ok(None);
+        };
+
+        let toolchain = crate::config::rust::toolchain::nightly(context.config, SPEC.name)?;
+
+        let status = match &*miri_subcommand {
+            "test" => {
+                let mut cmd = Command::new("cargo");
+                cmd.current_dir(context.current_dir());
+                cmd.args([&format!("+{toolchain}"), "miri"]);
+                cmd.args([miri_subcommand]);
+                cmd.args(context.tool_args);
+                context.runner.status(&context.options, &mut cmd)?
+            },
+            _ => unreachable!("unrecognized subcommands are rejected by `Spec::parse`"),
+        };
+
+        Ok(status)
+    }
 }
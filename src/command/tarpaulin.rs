@@ -1,42 +1,65 @@
-use crate::{command::Context, BoxResult};
-use std::process::{Command, ExitStatus};
-
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn tarpaulin(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-tarpaulin
-
-USAGE:
-xtask tarpaulin
-
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cargo command
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+use crate::{
+    command::{Context, Task},
+    executor::Invocation,
+    spec::Spec,
+    BoxResult,
+};
+use std::{
+    ffi::OsString,
+    process::{Command, ExitStatus},
+};
+
+static SPEC: Spec = Spec::new("tarpaulin", "Measure the project's test coverage with cargo-tarpaulin")
+    .flags(&crate::packages::FLAGS)
+    .tool("cargo command");
+
+pub struct Tarpaulin;
+
+impl Task for Tarpaulin {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
     }
 
-    crate::handler::unused(context.args)?;
+    /// The package selection flags are passed on to `xtask build`, which otherwise builds its own default selection.
+    fn dependencies(&self, args: &[OsString]) -> Vec<Invocation> {
+        let mut line = String::from("build");
+        for arg in crate::packages::forward(args).unwrap_or_default() {
+            line.push(' ');
+            line.push_str(&arg);
+        }
+        vec![Invocation::parse(&line)]
+    }
 
-    let toolchain = crate::config::rust::toolchain::nightly(context.config);
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
 
-    let mut cmd = Command::new("cargo");
-    cmd.current_dir(crate::workspace::project_root()?);
-    cmd.args([&format!("+{toolchain}"), "tarpaulin"]);
-    cmd.args(["--packages", "cxx-auto"]);
-    cmd.args(["--timeout", "120"]);
-    cmd.args(["--out", "Xml"]);
-    cmd.args(context.tool_args);
-    let status = cmd.status()?;
+        let packages = crate::packages::select(context.config, SPEC.name, &matches)?;
 
-    Ok(Some(status))
+        let toolchain = crate::config::rust::toolchain::nightly(context.config, SPEC.name)?;
+
+        let mut cmd = Command::new("cargo");
+        cmd.current_dir(context.current_dir());
+        cmd.args([&format!("+{toolchain}"), "tarpaulin"]);
+        for package in &packages {
+            cmd.args(["--packages", package]);
+        }
+        let tarpaulin = &context.config.xtask.tarpaulin;
+        cmd.args(["--timeout", &tarpaulin.timeout.to_string()]);
+        for out in &tarpaulin.out {
+            cmd.args(["--out", out]);
+        }
+        cmd.args(context.tool_args);
+        let status = context.runner.status(&context.options, &mut cmd)?;
+
+        Ok(status)
+    }
 }
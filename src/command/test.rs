@@ -1,38 +1,44 @@
-use crate::{command::Context, BoxResult};
+use crate::{
+    command::{Context, Task},
+    spec::Spec,
+    BoxResult,
+};
 use std::process::{Command, ExitStatus};
 
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn test(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-test
-
-USAGE:
-xtask test
-
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cargo command
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
-    }
-
-    crate::handler::unused(context.args)?;
+static SPEC: Spec = Spec::new("test", "Run the project's tests")
+    .flags(&crate::packages::FLAGS)
+    .tool("cargo command");
 
-    let mut cmd = Command::new("cargo");
-    cmd.current_dir(crate::workspace::project_root()?);
-    cmd.args(["test"]);
-    cmd.args(["--package", "cxx-auto"]);
-    cmd.args(context.tool_args);
+pub struct Test;
 
-    let status = cmd.status()?;
+impl Task for Test {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
+    }
 
-    Ok(Some(status))
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
+
+        let packages = crate::packages::select(context.config, SPEC.name, &matches)?;
+
+        let mut cmd = Command::new("cargo");
+        cmd.current_dir(context.current_dir());
+        cmd.args(["test"]);
+        for package in &packages {
+            cmd.args(["--package", package]);
+        }
+        cmd.args(context.tool_args);
+
+        let status = context.runner.status(&context.options, &mut cmd)?;
+
+        Ok(status)
+    }
 }
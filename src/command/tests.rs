@@ -0,0 +1,382 @@
+use crate::{
+    command::{self, Context, Task},
+    config::{CMakeContext, Config, RustToolchain, RustToolchainToolchain, XtaskCMakeBuildDir, XtaskConfig},
+    registry::Registry,
+    runner::{Recorded, RecordingRunner},
+    BoxResult,
+};
+use std::{ffi::OsString, path::PathBuf, sync::Arc};
+
+const WORKSPACE_ROOT: &str = "/work/demo";
+
+fn config() -> Config {
+    let cargo_metadata = serde_json::json!({
+        "packages": [package("demo"), package("demo-sys")],
+        "workspace_members": [id("demo"), id("demo-sys")],
+        "resolve": null,
+        "workspace_root": WORKSPACE_ROOT,
+        "target_directory": format!("{WORKSPACE_ROOT}/target"),
+        "version": 1,
+    });
+    let cargo_metadata = serde_json::from_value(cargo_metadata).expect("valid metadata");
+    Config::new(cargo_metadata, XtaskConfig::default())
+        .with_cmake_context(CMakeContext {
+            bin_clang_format: "/opt/llvm/bin/clang-format".into(),
+            bin_clang_tidy: "/opt/llvm/bin/clang-tidy".into(),
+            bin_run_clang_format: "/opt/llvm/bin/run-clang-format.py".into(),
+            bin_run_clang_tidy: "/opt/llvm/bin/run-clang-tidy".into(),
+        })
+        .with_rust_toolchain(RustToolchain {
+            toolchain: RustToolchainToolchain {
+                channel: String::from("nightly-2023-08-08"),
+                path: None,
+                profile: None,
+                components: vec![],
+                targets: vec![],
+            },
+        })
+}
+
+fn id(name: &str) -> String {
+    format!("{name} 0.1.0 (path+file://{WORKSPACE_ROOT}/{name})")
+}
+
+fn package(name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": name,
+        "version": "0.1.0",
+        "id": id(name),
+        "dependencies": [],
+        "targets": [],
+        "features": {},
+        "manifest_path": format!("{WORKSPACE_ROOT}/{name}/Cargo.toml"),
+    })
+}
+
+fn run(task: &dyn Task, config: &Config, args: &[&str], tool_args: &[&str]) -> BoxResult<Vec<Recorded>> {
+    let runner = RecordingRunner::new();
+    let mut args = pico_args::Arguments::from_vec(args.iter().map(OsString::from).collect());
+    let tool_args = tool_args.iter().map(OsString::from).collect();
+    let mut context = Context::new(config, &mut args, tool_args);
+    context.runner = &runner;
+    task.run(context)?;
+    Ok(runner.take())
+}
+
+/// Asserts that exactly one process was recorded, with `argv` and run from the workspace root.
+fn assert_single(recorded: &[Recorded], argv: &[&str]) {
+    let [recorded] = recorded else {
+        panic!("expected exactly one process, got {}", recorded.len());
+    };
+    assert_eq!(recorded.argv(), argv);
+    assert_eq!(recorded.current_dir, Some(PathBuf::from(WORKSPACE_ROOT)));
+}
+
+#[test]
+fn build() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Build, &config, &[], &[])?;
+    assert_single(&recorded, &[
+        "cargo",
+        "build",
+        "--package",
+        "demo",
+        "--package",
+        "demo-sys",
+    ]);
+    let recorded = run(&command::Build, &config, &["-p", "demo"], &["--release"])?;
+    assert_single(&recorded, &["cargo", "build", "--package", "demo", "--release"]);
+    Ok(())
+}
+
+#[test]
+fn check() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Check, &config, &["--exclude", "demo-sys"], &[])?;
+    assert_single(&recorded, &["cargo", "check", "--package", "demo"]);
+    Ok(())
+}
+
+#[test]
+fn check_rejects_empty_selection() {
+    let config = config();
+    let result = run(
+        &command::Check,
+        &config,
+        &["--exclude", "demo", "--exclude", "demo-sys"],
+        &[],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn clang_format() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Clang, &config, &["format"], &[])?;
+    assert_single(&recorded, &[
+        "/opt/llvm/bin/run-clang-format.py",
+        "--clang-format-executable",
+        "/opt/llvm/bin/clang-format",
+    ]);
+    let recorded = run(&command::Clang, &config, &["format"], &[
+        "--clang-format-executable",
+        "clang-format-16",
+    ])?;
+    assert_single(&recorded, &[
+        "/opt/llvm/bin/run-clang-format.py",
+        "--clang-format-executable",
+        "clang-format-16",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn clang_tidy() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Clang, &config, &["tidy"], &["-fix"])?;
+    assert_single(&recorded, &[
+        "/opt/llvm/bin/run-clang-tidy",
+        "-clang-tidy-binary",
+        "/opt/llvm/bin/clang-tidy",
+        "-fix",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn clippy() -> BoxResult<()> {
+    let mut config = config();
+    let recorded = run(&command::Clippy, &config, &["-p", "demo"], &[])?;
+    assert_single(&recorded, &[
+        "cargo",
+        "+nightly-2023-08-08",
+        "clippy",
+        "--package",
+        "demo",
+        "--",
+        "-D",
+        "warnings",
+    ]);
+    config.xtask.clippy.deny_warnings = false;
+    let recorded = run(&command::Clippy, &config, &["-p", "demo"], &["--all-targets"])?;
+    assert_single(&recorded, &[
+        "cargo",
+        "+nightly-2023-08-08",
+        "clippy",
+        "--package",
+        "demo",
+        "--all-targets",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn cmake_build() -> BoxResult<()> {
+    let mut config = config();
+    config.xtask.cmake.generator = String::from("Unix Makefiles");
+    let recorded = run(&command::CMake, &config, &["build"], &[])?;
+    assert_single(&recorded, &["cmake", "-G", "Unix Makefiles", "-S", ".", "-B", "build"]);
+    Ok(())
+}
+
+#[test]
+fn cmake_build_dirs() -> BoxResult<()> {
+    let mut config = config();
+    let cmake = &mut config.xtask.cmake;
+    cmake.source_dir = "cxx".into();
+    cmake.build_dir = String::from("debug");
+    cmake.build_dirs.insert(String::from("debug"), XtaskCMakeBuildDir {
+        path: None,
+        args: vec![String::from("-DCMAKE_BUILD_TYPE=Debug")],
+    });
+    cmake.build_dirs.insert(String::from("asan"), XtaskCMakeBuildDir {
+        path: Some("out/asan".into()),
+        args: vec![String::from("-DSANITIZE=address")],
+    });
+    let recorded = run(&command::CMake, &config, &["build"], &[])?;
+    assert_single(&recorded, &[
+        "cmake",
+        "-G",
+        "Ninja",
+        "-S",
+        "cxx",
+        "-B",
+        "build/debug",
+        "-DCMAKE_BUILD_TYPE=Debug",
+    ]);
+    let config = config.with_build_dir(Some(String::from("asan")))?;
+    assert_eq!(config.cmake_context_path(), "/work/demo/out/asan/cxx-auto-context.json");
+    let recorded = run(&command::CMake, &config, &["build"], &[])?;
+    assert_single(&recorded, &[
+        "cmake",
+        "-G",
+        "Ninja",
+        "-S",
+        "cxx",
+        "-B",
+        "out/asan",
+        "-DSANITIZE=address",
+    ]);
+    let config = config.with_build_dir(Some(String::from("/tmp/scratch")))?;
+    let recorded = run(&command::CMake, &config, &["build"], &[])?;
+    assert_single(&recorded, &["cmake", "-G", "Ninja", "-S", "cxx", "-B", "/tmp/scratch"]);
+    let Err(err) = config.with_build_dir(Some(String::from("asn"))) else {
+        panic!("a misspelled build directory name is accepted");
+    };
+    assert!(err.to_string().contains("did you mean `asan`?"));
+    Ok(())
+}
+
+#[test]
+fn doc() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Doc, &config, &[], &["--no-deps"])?;
+    assert_single(&recorded, &["cargo", "+nightly-2023-08-08", "doc", "--no-deps"]);
+    Ok(())
+}
+
+#[test]
+fn fmt() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Fmt, &config, &[], &["--check"])?;
+    assert_single(&recorded, &["cargo", "+nightly-2023-08-08", "fmt", "--all", "--check"]);
+    Ok(())
+}
+
+#[test]
+fn miri_test() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Miri, &config, &["test"], &[])?;
+    assert_single(&recorded, &["cargo", "+nightly-2023-08-08", "miri", "test"]);
+    Ok(())
+}
+
+#[test]
+fn tarpaulin() -> BoxResult<()> {
+    let mut config = config();
+    config.xtask.tarpaulin.packages = Some(vec![String::from("demo-sys")]);
+    let recorded = run(&command::Tarpaulin, &config, &[], &[])?;
+    assert_single(&recorded, &[
+        "cargo",
+        "+nightly-2023-08-08",
+        "tarpaulin",
+        "--packages",
+        "demo-sys",
+        "--timeout",
+        "120",
+        "--out",
+        "Xml",
+    ]);
+    let recorded = run(&command::Tarpaulin, &config, &["-p", "demo"], &[])?;
+    assert_eq!(recorded[0].argv()[3 .. 5], ["--packages", "demo"]);
+    config.xtask.tarpaulin.packages = None;
+    let recorded = run(&command::Tarpaulin, &config, &["--exclude", "demo"], &[])?;
+    assert_eq!(recorded[0].argv()[3 .. 6], ["--packages", "demo-sys", "--timeout"]);
+    Ok(())
+}
+
+#[test]
+fn test() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Test, &config, &["--workspace"], &[])?;
+    assert_single(&recorded, &[
+        "cargo",
+        "test",
+        "--package",
+        "demo",
+        "--package",
+        "demo-sys",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn udeps() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Udeps, &config, &["-p", "demo-sys"], &[])?;
+    assert_single(&recorded, &[
+        "cargo",
+        "+nightly-2023-08-08",
+        "udeps",
+        "--package",
+        "demo-sys",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn valgrind_test() -> BoxResult<()> {
+    let config = config();
+    let recorded = run(&command::Valgrind, &config, &["test"], &[])?;
+    assert_single(&recorded, &["cargo", "valgrind", "test", "--features", "valgrind"]);
+    Ok(())
+}
+
+#[test]
+fn current_dir_overrides_workspace_root() -> BoxResult<()> {
+    let config = config();
+    let runner = RecordingRunner::new();
+    let mut args = pico_args::Arguments::from_vec(vec![]);
+    let mut context = Context::new(&config, &mut args, vec![]);
+    context.runner = &runner;
+    context.current_dir = Some("/elsewhere".into());
+    command::Doc.run(context)?;
+    let recorded = runner.take();
+    assert_eq!(recorded[0].current_dir, Some(PathBuf::from("/elsewhere")));
+    Ok(())
+}
+
+#[test]
+fn ci_runs_steps_and_prerequisites_once() -> BoxResult<()> {
+    let mut config = config();
+    config.xtask.ci.steps = ["check", "clang tidy", "tarpaulin"].map(String::from).to_vec();
+    let runner = Arc::new(RecordingRunner::new());
+    let mut registry = Registry::default();
+    registry.runner(runner.clone());
+    let options = crate::options::Options {
+        verbosity: crate::options::Verbosity::Quiet,
+        ..Default::default()
+    };
+    registry.invoke(&config, &options, vec![OsString::from("ci")])?;
+    let programs = runner
+        .take()
+        .iter()
+        .map(|recorded| recorded.argv()[.. 3].join(" "))
+        .collect::<Vec<_>>();
+    assert_eq!(programs, [
+        "cargo check --package",
+        "cmake -G Ninja",
+        "/opt/llvm/bin/run-clang-tidy -clang-tidy-binary /opt/llvm/bin/clang-tidy",
+        "cargo build --package",
+        "cargo +nightly-2023-08-08 tarpaulin",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn completions_offer_global_flags_in_every_scope() {
+    let registry = Registry::default();
+    let script = crate::completions::Shell::Bash.generate(&registry, "xtask");
+    let scope = |path: &str| {
+        script
+            .lines()
+            .find(|line| line.trim_start().starts_with(&format!("'{path}')")))
+            .unwrap_or_else(|| panic!("no scope for `{path}`"))
+            .to_owned()
+    };
+    for path in ["xtask", "xtask;clang", "xtask;clang;tidy", "xtask;build"] {
+        assert!(scope(path).contains("--dry-run"), "`{path}` lacks the global flags");
+    }
+    assert!(scope("xtask;build").contains("--package"));
+}
+
+#[test]
+fn reloaded_config_forgets_loaded_files() {
+    let config = config()
+        .with_build_dir(Some(String::from("/tmp/scratch")))
+        .expect("a path");
+    assert!(config.cmake_context_loaded());
+    let reloaded = config.reloaded();
+    assert!(!reloaded.cmake_context_loaded());
+    assert_eq!(reloaded.cmake_context_path(), config.cmake_context_path());
+}
@@ -1,55 +1,54 @@
-use crate::{command::Context, BoxResult};
+use crate::{
+    command::{Context, Task},
+    spec::{Spec, Subcommand},
+    BoxResult,
+};
 use std::process::{Command, ExitStatus};
 
-/// # Errors
-///
-/// Will return `Err` under the following circumstances:
-/// - Argument processing fails (e.g. invalid arguments)
-/// - Tool validation fails (missing tools, incorrect versions, etc.)
-/// - The command process fails to start
-/// - The command invocation fails with non-zero exit status
-pub fn valgrind(context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
-    let help = r#"
-xtask-valgrind
+static SPEC: Spec = Spec::new("valgrind", "Run the project's tests with cargo-valgrind")
+    .subcommands(&[Subcommand::new("test", "Run the project's tests with cargo-valgrind")])
+    .tool("cargo command");
 
-USAGE:
-xtask valgrind [SUBCOMMAND]
+pub struct Valgrind;
 
-FLAGS:
--h, --help          Prints help information
--- '...'            Extra arguments to pass to the cargo command
-
-SUBCOMMANDS:
-    test            Run the project's tests  with cargo-valgrind
-"#
-    .trim();
-
-    if crate::handler::help(context.args, help)? {
-        return Ok(None);
+impl Task for Valgrind {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
     }
 
-    let Some(valgrind_subcommand) = context.args.opt_free_from_str::<String>()? else {
-        println!("{help}\n");
-        return Ok(None);
-    };
-
-    crate::handler::unused(context.args)?;
-
-    let status = match &*valgrind_subcommand {
-        "test" => {
-            let mut cmd = Command::new("cargo");
-            cmd.current_dir(crate::workspace::project_root()?);
-            cmd.args(["valgrind"]);
-            cmd.args([valgrind_subcommand]);
-            cmd.args(["--features", "valgrind"]);
-            cmd.args(context.tool_args);
-            cmd.status()?
-        },
-        _ => {
-            println!("{help}\n");
-            return Err(format!("unrecognized `xtask valgrind` subcommand `{valgrind_subcommand}`").into());
-        },
-    };
-
-    Ok(Some(status))
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - Tool validation fails (missing tools, incorrect versions, etc.)
+    /// - The command process fails to start
+    /// - The command invocation fails with non-zero exit status
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let Some(matches) = SPEC.parse(context.args)? else {
+            return Ok(None);
+        };
+
+        let Some(valgrind_subcommand) = matches.subcommand else {
+            println!("{}\n", SPEC.help());
+            return Ok(None);
+        };
+
+        let status = match &*valgrind_subcommand {
+            "test" => {
+                let mut cmd = Command::new("cargo");
+                cmd.current_dir(context.current_dir());
+                cmd.args(["valgrind"]);
+                cmd.args([valgrind_subcommand]);
+                let features = &context.config.xtask.valgrind.features;
+                if !features.is_empty() {
+                    cmd.args(["--features", &features.join(",")]);
+                }
+                cmd.args(context.tool_args);
+                context.runner.status(&context.options, &mut cmd)?
+            },
+            _ => unreachable!("unrecognized subcommands are rejected by `Spec::parse`"),
+        };
+
+        Ok(status)
+    }
 }
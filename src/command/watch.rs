@@ -0,0 +1,138 @@
+use crate::{
+    cache::Inputs,
+    command::{Context, Task},
+    config::Config,
+    executor::{Executor, Invocation},
+    options::Verbosity,
+    sources::Sources,
+    spec::Spec,
+    BoxResult,
+};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::{Duration, SystemTime},
+};
+
+static SPEC: Spec =
+    Spec::new("watch", "Rerun an xtask command whenever the workspace sources change").tool("watched command");
+
+/// How often the watched files are scanned for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the watched files must stay unchanged before the command is rerun.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub struct Watch;
+
+impl Task for Watch {
+    fn spec(&self) -> &'static Spec {
+        &SPEC
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - The command is not run through a [`Registry`](crate::registry::Registry)
+    /// - Scanning the watched directories or clearing the screen fails
+    /// - The prerequisites of the watched command form a cycle
+    fn run(&self, context: Context<'_>) -> BoxResult<Option<ExitStatus>> {
+        let mut args = std::mem::replace(context.args, pico_args::Arguments::from_vec(vec![])).finish();
+        if args.is_empty() {
+            println!("{}\n", SPEC.help());
+            return Ok(None);
+        }
+
+        let registry = context
+            .registry
+            .ok_or("`xtask watch` must be dispatched through a `Registry`")?;
+        let label = args
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !context.tool_args.is_empty() {
+            args.push("--".into());
+            args.extend(context.tool_args);
+        }
+        let invocation = || Invocation {
+            label: label.clone(),
+            args: args.clone(),
+        };
+
+        let roots = Roots::new(context.config);
+        let mut snapshot = roots.snapshot()?;
+        loop {
+            print!("\x1b[2J\x1b[H");
+            std::io::stdout().flush()?;
+            // Each run sees the `CMake` context as it is now, which e.g. the `cmake build` prerequisite of `clang tidy`
+            // may have regenerated during the previous run.
+            let config = context.config.reloaded();
+            crate::configure::reset();
+            Executor::new(registry, &config, &context.options).run(vec![invocation()], false)?;
+            if Verbosity::current() > Verbosity::Quiet {
+                eprintln!("[xtask] watching for changes to rerun `{label}`");
+            }
+            snapshot = roots.changed(snapshot)?;
+        }
+    }
+}
+
+/// The files watched by `xtask watch`: everything in the workspace members plus the C++ sources, except hidden files
+/// other than configuration files such as `.clang-format`, so that e.g. the swap files of editors are not watched.
+struct Roots {
+    sources: Sources,
+}
+
+/// The modification time and size of every watched file.
+type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+impl Roots {
+    fn new(config: &Config) -> Self {
+        Self {
+            sources: Sources::new(config),
+        }
+    }
+
+    /// Blocks until a watched file changes and stays unchanged for [`DEBOUNCE`], then returns the new snapshot.
+    fn changed(&self, mut snapshot: Snapshot) -> BoxResult<Snapshot> {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = self.snapshot()?;
+            if current != snapshot {
+                snapshot = current;
+                break;
+            }
+        }
+        loop {
+            std::thread::sleep(DEBOUNCE);
+            let current = self.snapshot()?;
+            if current == snapshot {
+                return Ok(snapshot);
+            }
+            snapshot = current;
+        }
+    }
+
+    fn snapshot(&self) -> BoxResult<Snapshot> {
+        let members = self.sources.members();
+        let files = self.sources.files(|path| {
+            !hidden(path) && (crate::sources::is_cxx(path) || members.iter().any(|member| path.starts_with(member)))
+        })?;
+        Ok(files
+            .into_iter()
+            .map(|(path, metadata)| (path, (metadata.modified().ok(), metadata.len())))
+            .collect())
+    }
+}
+
+/// Returns whether `path` is a hidden file which is not an input of any command.
+fn hidden(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    name.starts_with('.')
+        && ![&Inputs::RUST, &Inputs::CXX]
+            .iter()
+            .any(|inputs| inputs.files.contains(&name))
+}
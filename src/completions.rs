@@ -0,0 +1,309 @@
+use crate::{error::XtaskError, options::Options, registry::Registry, spec::Flag, BoxError};
+use std::{fmt::Write, str::FromStr};
+
+/// A shell for which completion scripts can be generated.
+#[derive(Clone, Copy)]
+pub enum Shell {
+    Bash,
+    Elvish,
+    Fish,
+    Zsh,
+}
+
+impl FromStr for Shell {
+    type Err = BoxError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "bash" => Ok(Self::Bash),
+            "elvish" => Ok(Self::Elvish),
+            "fish" => Ok(Self::Fish),
+            "zsh" => Ok(Self::Zsh),
+            _ => Err(XtaskError::bad_arguments(format!("unsupported shell `{str}`")).into()),
+        }
+    }
+}
+
+impl Shell {
+    /// Generates a completion script for the commands in `registry`, invoked as `bin`.
+    #[must_use]
+    pub fn generate(self, registry: &Registry, bin: &str) -> String {
+        let scopes = scopes(registry, bin);
+        match self {
+            Self::Bash => bash(&scopes, bin),
+            Self::Elvish => elvish(&scopes, bin),
+            Self::Fish => fish(&scopes, bin),
+            Self::Zsh => zsh(&scopes, bin),
+        }
+    }
+}
+
+static HELP: Flag = Flag::new("--help", "Prints help information").short("-h");
+
+/// The completion candidates offered after a particular sequence of (sub)command words.
+///
+/// The path is the `;`-separated sequence of words, starting with the binary name, e.g. `xtask;clang;tidy`.
+struct Scope {
+    path: String,
+    words: Vec<(&'static str, &'static str)>,
+    flags: Vec<&'static Flag>,
+}
+
+fn scopes(registry: &Registry, bin: &str) -> Vec<Scope> {
+    let mut scopes = vec![Scope {
+        path: bin.to_owned(),
+        words: registry
+            .tasks()
+            .map(|task| (task.spec().name, task.spec().about))
+            .collect(),
+        flags: std::iter::once(&HELP).chain(Options::FLAGS).collect(),
+    }];
+    for task in registry.tasks() {
+        let spec = task.spec();
+        let path = format!("{bin};{}", spec.name);
+        // The global options are accepted anywhere before `--`.
+        let flags = || {
+            std::iter::once(&HELP)
+                .chain(spec.flags)
+                .chain(Options::FLAGS)
+                .collect::<Vec<_>>()
+        };
+        for subcommand in spec.subcommands {
+            scopes.push(Scope {
+                path: format!("{path};{}", subcommand.name),
+                words: vec![],
+                flags: flags(),
+            });
+        }
+        scopes.push(Scope {
+            path,
+            words: spec
+                .subcommands
+                .iter()
+                .map(|subcommand| (subcommand.name, subcommand.about))
+                .collect(),
+            flags: flags(),
+        });
+    }
+    scopes
+}
+
+/// Returns the flags across all scopes which consume the following word as their value.
+fn value_flags(scopes: &[Scope]) -> Vec<&'static str> {
+    let mut words = vec![];
+    for flag in scopes.iter().flat_map(|scope| &scope.flags) {
+        if flag.value.is_some() {
+            words.extend(flag.short);
+            words.push(flag.long);
+        }
+    }
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+/// Returns the first line of `about` with runs of whitespace collapsed.
+fn describe(about: &str) -> String {
+    let line = about.lines().next().unwrap_or_default();
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn function_name(bin: &str) -> String {
+    let name = bin.replace(|char: char| !char.is_ascii_alphanumeric(), "_");
+    format!("_{name}")
+}
+
+/// Quotes `str` as a single-quoted POSIX shell word.
+fn quote_posix(str: &str) -> String {
+    format!("'{}'", str.replace('\'', r"'\''"))
+}
+
+fn bash(scopes: &[Scope], bin: &str) -> String {
+    let function = function_name(bin);
+    let mut script = String::new();
+    writeln!(script, "{function}() {{").ok();
+    writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"").ok();
+    writeln!(script, "    local cmdpath={} word i", quote_posix(bin)).ok();
+    writeln!(script, "    for ((i = 1; i < COMP_CWORD; i++)); do").ok();
+    writeln!(script, "        word=\"${{COMP_WORDS[i]}}\"").ok();
+    writeln!(script, "        case \"$word\" in").ok();
+    writeln!(script, "            --) return 0 ;;").ok();
+    let value_flags = value_flags(scopes);
+    if !value_flags.is_empty() {
+        writeln!(script, "            {}) ((i++)) ;;", value_flags.join("|")).ok();
+    }
+    writeln!(script, "            -*) ;;").ok();
+    writeln!(script, "            *) cmdpath=\"$cmdpath;$word\" ;;").ok();
+    writeln!(script, "        esac").ok();
+    writeln!(script, "    done").ok();
+    writeln!(script, "    local candidates=\"\"").ok();
+    writeln!(script, "    case \"$cmdpath\" in").ok();
+    for scope in scopes {
+        let mut candidates = scope.words.iter().map(|(word, _)| *word).collect::<Vec<_>>();
+        for flag in &scope.flags {
+            candidates.extend(flag.short);
+            candidates.push(flag.long);
+        }
+        let path = quote_posix(&scope.path);
+        let candidates = quote_posix(&candidates.join(" "));
+        writeln!(script, "        {path}) candidates={candidates} ;;").ok();
+    }
+    writeln!(script, "    esac").ok();
+    writeln!(script, "    COMPREPLY=($(compgen -W \"$candidates\" -- \"$cur\"))").ok();
+    writeln!(script, "}}\n").ok();
+    writeln!(script, "complete -F {function} -o default {bin}").ok();
+    script
+}
+
+fn zsh(scopes: &[Scope], bin: &str) -> String {
+    let function = function_name(bin);
+    let mut script = String::new();
+    writeln!(script, "#compdef {bin}\n").ok();
+    writeln!(script, "{function}() {{").ok();
+    writeln!(script, "    local cmdpath={} word i", quote_posix(bin)).ok();
+    writeln!(script, "    local -a candidates").ok();
+    writeln!(script, "    for ((i = 2; i < CURRENT; i++)); do").ok();
+    writeln!(script, "        word=\"${{words[i]}}\"").ok();
+    writeln!(script, "        case \"$word\" in").ok();
+    writeln!(script, "            --) return 0 ;;").ok();
+    let value_flags = value_flags(scopes);
+    if !value_flags.is_empty() {
+        writeln!(script, "            {}) ((i++)) ;;", value_flags.join("|")).ok();
+    }
+    writeln!(script, "            -*) ;;").ok();
+    writeln!(script, "            *) cmdpath=\"$cmdpath;$word\" ;;").ok();
+    writeln!(script, "        esac").ok();
+    writeln!(script, "    done").ok();
+    writeln!(script, "    case \"$cmdpath\" in").ok();
+    for scope in scopes {
+        let mut candidates = vec![];
+        for (word, about) in &scope.words {
+            candidates.push(format!("{word}:{}", describe(about).replace(':', r"\:")));
+        }
+        for flag in &scope.flags {
+            let about = describe(flag.about).replace(':', r"\:");
+            candidates.extend(flag.short.map(|short| format!("{short}:{about}")));
+            candidates.push(format!("{}:{about}", flag.long));
+        }
+        let candidates = candidates.iter().map(|str| quote_posix(str)).collect::<Vec<_>>();
+        writeln!(script, "        {})", quote_posix(&scope.path)).ok();
+        writeln!(script, "            candidates=({})", candidates.join(" ")).ok();
+        writeln!(script, "            ;;").ok();
+    }
+    writeln!(script, "    esac").ok();
+    writeln!(script, "    _describe -t commands {} candidates", quote_posix(bin)).ok();
+    writeln!(script, "}}\n").ok();
+    writeln!(script, "if [ \"$funcstack[1]\" = \"{function}\" ]; then").ok();
+    writeln!(script, "    {function} \"$@\"").ok();
+    writeln!(script, "else").ok();
+    writeln!(script, "    compdef {function} {bin}").ok();
+    writeln!(script, "fi").ok();
+    script
+}
+
+/// Quotes `str` as a single-quoted fish word.
+fn quote_fish(str: &str) -> String {
+    format!("'{}'", str.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+fn fish(scopes: &[Scope], bin: &str) -> String {
+    let function = format!("_{}_path", function_name(bin));
+    let mut script = String::new();
+    writeln!(script, "function {function}").ok();
+    writeln!(script, "    set -l tokens (commandline -opc)").ok();
+    writeln!(script, "    set -e tokens[1]").ok();
+    writeln!(script, "    set -l cmdpath {}", quote_fish(bin)).ok();
+    writeln!(script, "    set -l skip 0").ok();
+    writeln!(script, "    for token in $tokens").ok();
+    writeln!(script, "        if test $skip -eq 1").ok();
+    writeln!(script, "            set skip 0").ok();
+    writeln!(script, "        else if test \"$token\" = '--'").ok();
+    writeln!(script, "            echo '--'").ok();
+    writeln!(script, "            return").ok();
+    let value_flags = value_flags(scopes);
+    if !value_flags.is_empty() {
+        writeln!(script, "        else if contains -- $token {}", value_flags.join(" ")).ok();
+        writeln!(script, "            set skip 1").ok();
+    }
+    writeln!(script, "        else if not string match -q -- '-*' $token").ok();
+    writeln!(script, "            set cmdpath \"$cmdpath;$token\"").ok();
+    writeln!(script, "        end").ok();
+    writeln!(script, "    end").ok();
+    writeln!(script, "    echo $cmdpath").ok();
+    writeln!(script, "end\n").ok();
+    writeln!(script, "complete -c {bin} -f").ok();
+    for scope in scopes {
+        let condition = quote_fish(&format!("test ({function}) = {}", quote_fish(&scope.path)));
+        for (word, about) in &scope.words {
+            let about = quote_fish(&describe(about));
+            writeln!(script, "complete -c {bin} -n {condition} -a {word} -d {about}").ok();
+        }
+        for flag in &scope.flags {
+            let mut line = format!("complete -c {bin} -n {condition}");
+            if let Some(short) = flag.short {
+                write!(line, " -s {}", short.trim_start_matches('-')).ok();
+            }
+            write!(line, " -l {}", flag.long.trim_start_matches('-')).ok();
+            if flag.value.is_some() {
+                line.push_str(" -r");
+            }
+            write!(line, " -d {}", quote_fish(&describe(flag.about))).ok();
+            writeln!(script, "{line}").ok();
+        }
+    }
+    script
+}
+
+/// Quotes `str` as a single-quoted elvish string.
+fn quote_elvish(str: &str) -> String {
+    format!("'{}'", str.replace('\'', "''"))
+}
+
+fn elvish(scopes: &[Scope], bin: &str) -> String {
+    let mut script = String::new();
+    writeln!(script, "use str\n").ok();
+    writeln!(script, "set edit:completion:arg-completer[{bin}] = {{|@words|").ok();
+    writeln!(script, "    fn cand {{|text desc|").ok();
+    writeln!(script, "        edit:complex-candidate $text &display=$text' '$desc").ok();
+    writeln!(script, "    }}").ok();
+    writeln!(script, "    var cmdpath = {}", quote_elvish(bin)).ok();
+    writeln!(script, "    var skip = $false").ok();
+    let value_flags = value_flags(scopes)
+        .iter()
+        .map(|flag| quote_elvish(flag))
+        .collect::<Vec<_>>();
+    writeln!(script, "    var value-flags = [{}]", value_flags.join(" ")).ok();
+    writeln!(script, "    for word $words[1..-1] {{").ok();
+    writeln!(script, "        if $skip {{").ok();
+    writeln!(script, "            set skip = $false").ok();
+    writeln!(script, "        }} elif (eq $word '--') {{").ok();
+    writeln!(script, "            return").ok();
+    writeln!(script, "        }} elif (has-value $value-flags $word) {{").ok();
+    writeln!(script, "            set skip = $true").ok();
+    writeln!(script, "        }} elif (not (str:has-prefix $word '-')) {{").ok();
+    writeln!(script, "            set cmdpath = $cmdpath';'$word").ok();
+    writeln!(script, "        }}").ok();
+    writeln!(script, "    }}").ok();
+    writeln!(script, "    var completions = [").ok();
+    for scope in scopes {
+        writeln!(script, "        &{}= {{", quote_elvish(&scope.path)).ok();
+        for (word, about) in &scope.words {
+            let about = quote_elvish(&describe(about));
+            writeln!(script, "            cand {word} {about}").ok();
+        }
+        for flag in &scope.flags {
+            let about = quote_elvish(&describe(flag.about));
+            if let Some(short) = flag.short {
+                writeln!(script, "            cand {short} {about}").ok();
+            }
+            writeln!(script, "            cand {} {about}", flag.long).ok();
+        }
+        writeln!(script, "        }}").ok();
+    }
+    writeln!(script, "    ]").ok();
+    writeln!(script, "    if (has-key $completions $cmdpath) {{").ok();
+    writeln!(script, "        $completions[$cmdpath]").ok();
+    writeln!(script, "    }}").ok();
+    writeln!(script, "}}").ok();
+    script
+}
@@ -1,6 +1,7 @@
-use crate::{BoxError, BoxResult};
-use camino::Utf8PathBuf;
+use crate::{error::XtaskError, BoxError, BoxResult};
+use camino::{Utf8Path, Utf8PathBuf};
 use serde::Deserialize;
+use std::{collections::BTreeMap, sync::OnceLock, time::Duration};
 
 #[cfg_attr(feature = "debug", derive(Debug))]
 #[derive(Deserialize)]
@@ -33,62 +34,546 @@ pub struct RustToolchainToolchain {
     pub targets: Vec<String>,
 }
 
+/// The `[workspace.metadata.xtask]` table of the workspace `Cargo.toml`.
+///
+/// Every section and field is optional and falls back to the defaults shown below:
+///
+/// ```toml
+/// [workspace.metadata.xtask.cache]
+/// enabled = false
+///
+/// [workspace.metadata.xtask.ci]
+/// steps = ["fmt -- --check", "clippy", "check", "test", "clang format", "clang tidy", "udeps", "tarpaulin"]
+/// keep-going = false
+///
+/// [workspace.metadata.xtask.clippy]
+/// deny-warnings = true
+///
+/// [workspace.metadata.xtask.cmake]
+/// generator = "Ninja"
+/// source-dir = "."
+/// build-dir = "build"
+///
+/// [workspace.metadata.xtask.cmake.build-dirs]
+/// # none by default, e.g.:
+/// # debug = { args = ["-DCMAKE_BUILD_TYPE=Debug"] }
+/// # asan = { path = "build/asan", args = ["-DCMAKE_BUILD_TYPE=Debug", "-DSANITIZE=address"] }
+///
+/// [workspace.metadata.xtask.tarpaulin]
+/// timeout = 120
+/// out = ["Xml"]
+///
+/// [workspace.metadata.xtask.timeouts]
+/// # none by default, e.g.:
+/// # miri = 3600
+/// # "clang tidy" = 600
+///
+/// [workspace.metadata.xtask.valgrind]
+/// features = ["valgrind"]
+/// ```
+///
+/// The sections for commands which operate on cargo packages additionally accept a `packages` array.
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct XtaskConfig {
+    pub build: XtaskCargoConfig,
+    pub cache: XtaskCacheConfig,
+    pub check: XtaskCargoConfig,
+    pub ci: XtaskCiConfig,
+    pub clippy: XtaskClippyConfig,
+    pub cmake: XtaskCMakeConfig,
+    pub tarpaulin: XtaskTarpaulinConfig,
+    pub test: XtaskCargoConfig,
+    /// Wall-clock timeouts in seconds, keyed on the command name (e.g. `miri`) or the command name and subcommand
+    /// (e.g. `clang tidy`).
+    pub timeouts: BTreeMap<String, u64>,
+    pub udeps: XtaskCargoConfig,
+    pub valgrind: XtaskValgrindConfig,
+}
+
+impl XtaskConfig {
+    /// Returns the configured packages for the command named `command`, if any.
+    #[must_use]
+    pub fn packages(&self, command: &str) -> Option<&[String]> {
+        let packages = match command {
+            "build" => &self.build.packages,
+            "check" => &self.check.packages,
+            "clippy" => &self.clippy.packages,
+            "tarpaulin" => &self.tarpaulin.packages,
+            "test" => &self.test.packages,
+            "udeps" => &self.udeps.packages,
+            _ => return None,
+        };
+        packages.as_deref()
+    }
+
+    /// Returns the configured timeout for the command named `command` run with `subcommand`, if any.
+    ///
+    /// A timeout for the subcommand takes precedence over one for the command as a whole.
+    #[must_use]
+    pub fn timeout(&self, command: &str, subcommand: Option<&str>) -> Option<Duration> {
+        subcommand
+            .and_then(|subcommand| self.timeouts.get(&format!("{command} {subcommand}")))
+            .or_else(|| self.timeouts.get(command))
+            .map(|&secs| Duration::from_secs(secs))
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct XtaskCargoConfig {
+    pub packages: Option<Vec<String>>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct XtaskCacheConfig {
+    /// Whether commands whose inputs are unchanged since their last successful run are skipped.
+    pub enabled: bool,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct XtaskCiConfig {
+    /// The commands to run, in order, each written as on the command line without the leading `xtask`.
+    pub steps: Vec<String>,
+    /// Whether to continue with the remaining steps after a step fails.
+    pub keep_going: bool,
+}
+
+impl Default for XtaskCiConfig {
+    fn default() -> Self {
+        let steps = [
+            "fmt -- --check",
+            "clippy",
+            "check",
+            "test",
+            "clang format",
+            "clang tidy",
+            "udeps",
+            "tarpaulin",
+        ];
+        Self {
+            steps: steps.into_iter().map(String::from).collect(),
+            keep_going: false,
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct XtaskClippyConfig {
+    pub packages: Option<Vec<String>>,
+    pub deny_warnings: bool,
+}
+
+impl Default for XtaskClippyConfig {
+    fn default() -> Self {
+        Self {
+            packages: None,
+            deny_warnings: true,
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct XtaskCMakeConfig {
+    pub generator: String,
+    /// The directory containing the top-level `CMakeLists.txt`, relative to the workspace root.
+    pub source_dir: Utf8PathBuf,
+    /// The build directory used unless `--build-dir` selects another: the name of one of the `build-dirs`, or a path
+    /// relative to the workspace root.
+    pub build_dir: String,
+    /// Named build directories, e.g. for different build types or sanitizers, which `--build-dir` can select.
+    pub build_dirs: BTreeMap<String, XtaskCMakeBuildDir>,
+}
+
+impl XtaskCMakeConfig {
+    /// Returns the build directory selected by `selection`, or by `build-dir` without one, relative to the workspace
+    /// root, together with the additional arguments to configure it with.
+    ///
+    /// A selection which names one of the `build-dirs` refers to that directory, and any other selection is a path.
+    #[must_use]
+    pub fn resolve_build_dir(&self, selection: Option<&str>) -> (Utf8PathBuf, &[String]) {
+        let selection = selection.unwrap_or(&self.build_dir);
+        match self.build_dirs.get(selection) {
+            Some(build_dir) => (build_dir.path(selection), &build_dir.args),
+            None => (Utf8PathBuf::from(selection), &[]),
+        }
+    }
+}
+
+impl Default for XtaskCMakeConfig {
+    fn default() -> Self {
+        Self {
+            generator: String::from("Ninja"),
+            source_dir: Utf8PathBuf::from("."),
+            build_dir: String::from("build"),
+            build_dirs: BTreeMap::new(),
+        }
+    }
+}
+
+/// A named `CMake` build directory, in the `[workspace.metadata.xtask.cmake.build-dirs]` table.
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct XtaskCMakeBuildDir {
+    /// The build directory, relative to the workspace root. Defaults to `build/<name>`.
+    pub path: Option<Utf8PathBuf>,
+    /// Additional arguments to `cmake` when configuring the build directory, e.g. `-DCMAKE_BUILD_TYPE=Debug`.
+    pub args: Vec<String>,
+}
+
+impl XtaskCMakeBuildDir {
+    /// Returns the path of the build directory named `name`, relative to the workspace root.
+    #[must_use]
+    pub fn path(&self, name: &str) -> Utf8PathBuf {
+        self.path.clone().unwrap_or_else(|| Utf8Path::new("build").join(name))
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct XtaskTarpaulinConfig {
+    pub packages: Option<Vec<String>>,
+    pub timeout: u64,
+    pub out: Vec<String>,
+}
+
+impl Default for XtaskTarpaulinConfig {
+    fn default() -> Self {
+        Self {
+            packages: None,
+            timeout: 120,
+            out: vec![String::from("Xml")],
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct XtaskValgrindConfig {
+    pub features: Vec<String>,
+}
+
+impl Default for XtaskValgrindConfig {
+    fn default() -> Self {
+        Self {
+            features: vec![String::from("valgrind")],
+        }
+    }
+}
+
+/// The configuration of a cxx-auto workspace.
+///
+/// The `cargo metadata` of the workspace and its `[workspace.metadata.xtask]` table are loaded up front. The `CMake`
+/// context and the rust toolchain are only loaded when first needed, since many commands need neither, and are then
+/// kept for later use.
 pub struct Config {
-    pub cmake_context: CMakeContext,
     pub cargo_metadata: cargo_metadata::Metadata,
-    pub rust_toolchain: RustToolchain,
+    pub xtask: XtaskConfig,
+    /// The `CMake` build directory selected with `--build-dir`, if any (see [`XtaskCMakeConfig::resolve_build_dir`]).
+    build_dir: Option<String>,
+    cmake_context: OnceLock<CMakeContext>,
+    rust_toolchain: OnceLock<RustToolchain>,
 }
 
 impl Config {
+    /// Creates the configuration of the workspace described by `cargo_metadata`, configured by `xtask`.
+    #[must_use]
+    pub fn new(cargo_metadata: cargo_metadata::Metadata, xtask: XtaskConfig) -> Self {
+        Self {
+            cargo_metadata,
+            xtask,
+            build_dir: None,
+            cmake_context: OnceLock::new(),
+            rust_toolchain: OnceLock::new(),
+        }
+    }
+
+    /// Selects the `CMake` build directory `build_dir`, which is either the name of one of the configured build
+    /// directories or a path relative to the workspace root, instead of the configured default.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `build_dir` is neither the name of a configured build directory nor a path, i.e. it contains
+    /// no path separator and does not exist, since it is then most likely a misspelled name.
+    pub fn with_build_dir(mut self, build_dir: Option<String>) -> BoxResult<Self> {
+        if let Some(name) = &build_dir {
+            let cmake = &self.xtask.cmake;
+            let path = name.contains(std::path::is_separator) || self.cargo_metadata.workspace_root.join(name).exists();
+            if !cmake.build_dirs.contains_key(name) && !path {
+                let mut suggestion = crate::suggest::did_you_mean(name, cmake.build_dirs.keys().map(String::as_str));
+                if suggestion.is_empty() {
+                    suggestion = format!("; use `./{name}` for a new directory of that name");
+                }
+                let message = format!("`--build-dir {name}` is not a configured build directory{suggestion}");
+                return Err(XtaskError::bad_arguments(message).into());
+            }
+        }
+        self.build_dir = build_dir;
+        Ok(self)
+    }
+
+    /// Returns a copy of the configuration which loads the `CMake` context and the rust toolchain anew when they are next
+    /// needed, e.g. after `xtask cmake build` has regenerated the context.
+    #[must_use]
+    pub fn reloaded(&self) -> Self {
+        let mut config = Self::new(self.cargo_metadata.clone(), self.xtask.clone());
+        config.build_dir = self.build_dir.clone();
+        config
+    }
+
+    /// Uses `cmake_context` instead of loading the `CMake` context from the workspace.
+    #[must_use]
+    pub fn with_cmake_context(self, cmake_context: CMakeContext) -> Self {
+        self.cmake_context.set(cmake_context).ok();
+        self
+    }
+
+    /// Uses `rust_toolchain` instead of loading the rust toolchain from the workspace.
+    #[must_use]
+    pub fn with_rust_toolchain(self, rust_toolchain: RustToolchain) -> Self {
+        self.rust_toolchain.set(rust_toolchain).ok();
+        self
+    }
+
+    /// Loads the configuration of the workspace containing the current directory.
+    ///
     /// # Errors
     ///
     /// Will return `Err` under the following circumstances:
     /// - `cargo metadata` fails
-    /// - Reading the `rust-toolchain.toml` file as text fails
+    /// - The `[workspace.metadata.xtask]` table is invalid
     pub fn load() -> BoxResult<Self> {
-        let cargo_metadata = cargo_metadata::MetadataCommand::new().exec()?;
-        let cmake_context = {
-            let path = cargo_metadata.workspace_root.join("build/cxx-auto-context.json");
-            let data = std::fs::read_to_string(&path).map_err(|err| {
-                if err.kind() == std::io::ErrorKind::NotFound {
-                    format!("Path not found: {}", path.as_std_path().display()).into()
-                } else {
-                    BoxError::from(err)
-                }
-            })?;
-            serde_json::from_str(&data)?
-        };
-        let rust_toolchain: RustToolchain = {
-            let path = cargo_metadata.workspace_root.join("rust-toolchain.toml");
-            let data = std::fs::read_to_string(&path).map_err(|err| {
-                if err.kind() == std::io::ErrorKind::NotFound {
-                    format!("Path not found: {}", path.as_std_path().display()).into()
-                } else {
-                    BoxError::from(err)
-                }
-            })?;
-            toml::from_str(&data)?
+        Self::load_from(None, None)
+    }
+
+    /// Loads the configuration of the workspace containing `dir`, or of the workspace whose manifest is at
+    /// `manifest_path` (relative to `dir`, if given).
+    ///
+    /// Without `dir`, the current directory is used, as for [`Config::load`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - `dir` is not a directory
+    /// - `cargo metadata` fails
+    /// - The `[workspace.metadata.xtask]` table is invalid
+    pub fn load_from(dir: Option<&Utf8Path>, manifest_path: Option<&Utf8Path>) -> BoxResult<Self> {
+        let mut metadata_command = cargo_metadata::MetadataCommand::new();
+        if let Some(dir) = dir {
+            if !dir.is_dir() {
+                return Err(XtaskError::bad_arguments(format!("`{dir}` is not a directory")).into());
+            }
+            metadata_command.current_dir(dir);
+        }
+        if let Some(manifest_path) = manifest_path {
+            metadata_command.manifest_path(manifest_path);
+        }
+        let cargo_metadata = metadata_command.exec()?;
+        let xtask = match cargo_metadata.workspace_metadata.get("xtask") {
+            Some(xtask) => XtaskConfig::deserialize(xtask)
+                .map_err(|err| format!("invalid `[workspace.metadata.xtask]` configuration: {err}"))?,
+            None => XtaskConfig::default(),
         };
-        Ok(Config {
-            cmake_context,
-            cargo_metadata,
-            rust_toolchain,
-        })
+        Ok(Config::new(cargo_metadata, xtask))
+    }
+
+    /// Returns the `CMake` context of the selected build directory, loading it from its `cxx-auto-context.json` on
+    /// first use.
+    ///
+    /// `command` names the xtask command which needs the context, e.g. `clang tidy`, for the error message.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - The `cxx-auto-context.json` file is missing ([`XtaskError::MissingConfig`])
+    /// - Reading or parsing the `cxx-auto-context.json` file fails
+    pub fn cmake_context(&self, command: &str) -> BoxResult<&CMakeContext> {
+        if let Some(cmake_context) = self.cmake_context.get() {
+            return Ok(cmake_context);
+        }
+        let cmake_context = self.load_cmake_context(command)?;
+        Ok(self.cmake_context.get_or_init(|| cmake_context))
+    }
+
+    fn load_cmake_context(&self, command: &str) -> BoxResult<CMakeContext> {
+        let path = self.cmake_context_path();
+        let data = read(&path, command)?;
+        Ok(serde_json::from_str(&data).map_err(|err| format!("invalid `{path}`: {err}"))?)
+    }
+
+    /// Returns the path of the `CMake` context file, which is written to the build directory when the `CMake` build is
+    /// configured.
+    #[must_use]
+    pub fn cmake_context_path(&self) -> Utf8PathBuf {
+        self.cmake_build_dir().join("cxx-auto-context.json")
+    }
+
+    /// Returns the `CMake` source directory of the workspace.
+    #[must_use]
+    pub fn cmake_source_dir(&self) -> Utf8PathBuf {
+        // Collecting the components drops any `.`, e.g. of the default `source-dir`.
+        let source_dir = self.cargo_metadata.workspace_root.join(&self.xtask.cmake.source_dir);
+        source_dir.components().collect()
+    }
+
+    /// Returns the selected `CMake` build directory.
+    #[must_use]
+    pub fn cmake_build_dir(&self) -> Utf8PathBuf {
+        let (build_dir, _) = self.xtask.cmake.resolve_build_dir(self.build_dir.as_deref());
+        self.cargo_metadata.workspace_root.join(build_dir)
+    }
+
+    /// Returns the additional arguments to `cmake` when configuring the selected build directory.
+    #[must_use]
+    pub fn cmake_build_args(&self) -> &[String] {
+        let (_, args) = self.xtask.cmake.resolve_build_dir(self.build_dir.as_deref());
+        args
+    }
+
+    /// Returns every `CMake` build directory of the workspace: the selected one, the default one and the named ones.
+    #[must_use]
+    pub fn cmake_build_dirs(&self) -> Vec<Utf8PathBuf> {
+        let cmake = &self.xtask.cmake;
+        let (default, _) = cmake.resolve_build_dir(None);
+        let mut build_dirs = vec![self.cmake_build_dir(), self.cargo_metadata.workspace_root.join(default)];
+        for (name, build_dir) in &cmake.build_dirs {
+            build_dirs.push(self.cargo_metadata.workspace_root.join(build_dir.path(name)));
+        }
+        build_dirs.sort();
+        build_dirs.dedup();
+        build_dirs
+    }
+
+    /// Returns whether the `CMake` context has been loaded, or was provided with [`Config::with_cmake_context`].
+    #[must_use]
+    pub fn cmake_context_loaded(&self) -> bool {
+        self.cmake_context.get().is_some()
+    }
+
+    /// Returns the path of the top-level `CMakeLists.txt` of the workspace.
+    #[must_use]
+    pub fn cmake_lists_path(&self) -> Utf8PathBuf {
+        self.cmake_source_dir().join("CMakeLists.txt")
+    }
+
+    /// Returns the rust toolchain of the workspace, loading it from `rust-toolchain.toml` on first use.
+    ///
+    /// `command` names the xtask command which needs the toolchain, as for [`Config::cmake_context`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - The `rust-toolchain.toml` file is missing ([`XtaskError::MissingConfig`])
+    /// - Reading or parsing the `rust-toolchain.toml` file fails
+    pub fn rust_toolchain(&self, command: &str) -> BoxResult<&RustToolchain> {
+        if let Some(rust_toolchain) = self.rust_toolchain.get() {
+            return Ok(rust_toolchain);
+        }
+        let rust_toolchain = self.load_rust_toolchain(command)?;
+        Ok(self.rust_toolchain.get_or_init(|| rust_toolchain))
+    }
+
+    fn load_rust_toolchain(&self, command: &str) -> BoxResult<RustToolchain> {
+        let path = self.cargo_metadata.workspace_root.join("rust-toolchain.toml");
+        let data = read(&path, command)?;
+        Ok(toml::from_str(&data).map_err(|err| format!("invalid `{path}`: {err}"))?)
+    }
+}
+
+/// Reads the configuration file at `path`, needed by the xtask command named `command`.
+fn read(path: &Utf8Path, command: &str) -> BoxResult<String> {
+    std::fs::read_to_string(path).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            XtaskError::MissingConfig {
+                path: path.to_owned(),
+                command: command.to_owned(),
+            }
+            .into()
+        } else {
+            BoxError::from(format!("failed to read `{path}`: {err}"))
+        }
+    })
+}
+
+impl Config {
+    /// Prints the resolved configuration values to stderr.
+    ///
+    /// The `CMake` context and the rust toolchain are read from disk, on behalf of the xtask command named `command`,
+    /// without being kept: the command loads them itself if it needs them, after checking that the `CMake` context is
+    /// up to date (see [`configure`](crate::configure)). Those which cannot be read are reported along with the reason.
+    pub fn report(&self, command: &str) {
+        let report = |key: &str, value: &dyn std::fmt::Display| eprintln!("[xtask] {key} = {value}");
+        report("workspace root", &self.cargo_metadata.workspace_root);
+        report("cmake source dir", &self.cmake_source_dir());
+        report("cmake build dir", &self.cmake_build_dir());
+        match self.load_rust_toolchain(command) {
+            Ok(rust_toolchain) => report("rust toolchain channel", &rust_toolchain.toolchain.channel),
+            Err(err) => report("rust toolchain", &format!("(unavailable: {err})")),
+        }
+        match self.load_cmake_context(command) {
+            Ok(cmake_context) => {
+                report("cmake context BIN_CLANG_FORMAT", &cmake_context.bin_clang_format);
+                report("cmake context BIN_CLANG_TIDY", &cmake_context.bin_clang_tidy);
+                report(
+                    "cmake context BIN_RUN_CLANG_FORMAT",
+                    &cmake_context.bin_run_clang_format,
+                );
+                report("cmake context BIN_RUN_CLANG_TIDY", &cmake_context.bin_run_clang_tidy);
+            },
+            Err(err) => report("cmake context", &format!("(unavailable: {err})")),
+        }
     }
 }
 
 pub mod rust {
     pub mod toolchain {
-        use crate::config::Config;
+        use crate::{config::Config, error::XtaskError, BoxResult};
 
         #[must_use]
         pub fn stable(_config: &Config) -> &str {
             "stable"
         }
 
-        #[must_use]
-        pub fn nightly(config: &Config) -> &str {
-            &config.rust_toolchain.toolchain.channel
+        /// Returns the nightly channel of the workspace, from its rust toolchain.
+        ///
+        /// # Errors
+        ///
+        /// Will return `Err` under the following circumstances:
+        /// - The rust toolchain, needed by the xtask command named `command`, cannot be loaded
+        /// - The channel of the rust toolchain is not a nightly channel ([`XtaskError::ToolVersion`])
+        pub fn nightly<'a>(config: &'a Config, command: &str) -> BoxResult<&'a str> {
+            let channel = &config.rust_toolchain(command)?.toolchain.channel;
+            if !channel.starts_with("nightly") {
+                return Err(XtaskError::ToolVersion {
+                    tool: String::from("rust-toolchain.toml"),
+                    expected: format!("a nightly channel for `xtask {command}`"),
+                    found: channel.clone(),
+                }
+                .into());
+            }
+            Ok(channel)
         }
     }
 }
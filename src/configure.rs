@@ -0,0 +1,120 @@
+use crate::{
+    command::{Context, Task},
+    config::{CMakeContext, Config},
+    error::XtaskError,
+    options::Verbosity,
+    BoxResult,
+};
+use std::{
+    ffi::OsString,
+    fmt,
+    io::{IsTerminal, Write},
+    sync::Mutex,
+};
+
+/// Why the `CMake` context file needs to be regenerated.
+enum Reason {
+    Missing,
+    Stale,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "is missing"),
+            Self::Stale => write!(f, "is older than `CMakeLists.txt`"),
+        }
+    }
+}
+
+/// Whether to configure the `CMake` build, once decided. Commands which need the context concurrently hold the lock
+/// while deciding and configuring, so that the user is asked, and the build configured, at most once.
+static DECISION: Mutex<Option<bool>> = Mutex::new(None);
+
+/// Forgets whether to configure the `CMake` build, so that a context file which is missing or stale again is regenerated
+/// or warned about anew, e.g. by the next run of `xtask watch`.
+pub(crate) fn reset() {
+    if let Ok(mut decision) = DECISION.lock() {
+        *decision = None;
+    }
+}
+
+/// Returns the `CMake` context for the xtask command named `command`, first configuring the `CMake` build with
+/// `xtask cmake build` if the context file is missing or older than `CMakeLists.txt`.
+///
+/// With `--auto-configure` the build is configured right away. Otherwise the user is asked, if xtask runs in a
+/// terminal. A stale context which is not regenerated is used as it is, after a warning.
+///
+/// # Errors
+///
+/// Will return `Err` under the following circumstances:
+/// - Configuring the `CMake` build fails
+/// - The `CMake` context cannot be loaded, as for [`Config::cmake_context`]
+pub(crate) fn cmake_context<'a>(context: &Context<'a>, command: &str) -> BoxResult<&'a CMakeContext> {
+    let config = context.config;
+    if !config.cmake_context_loaded() {
+        let mut decision = DECISION
+            .lock()
+            .map_err(|_| "the `CMake` configuration lock is poisoned")?;
+        if let Some(reason) = reason(config) {
+            let configure = *decision.get_or_insert_with(|| ask(context, command, &reason));
+            if configure {
+                // Whatever the outcome, the build is not configured again for other commands.
+                *decision = Some(false);
+                run(context, command)?;
+            } else if let Reason::Stale = reason {
+                let path = config.cmake_context_path();
+                crate::process::eprintln(&format!(
+                    "warning: `{path}` {reason}; run `xtask cmake build` to update it"
+                ));
+            }
+        }
+    }
+    config.cmake_context(command)
+}
+
+/// Returns why the `CMake` context file of `config` needs to be regenerated, if it does.
+fn reason(config: &Config) -> Option<Reason> {
+    let Ok(metadata) = std::fs::metadata(config.cmake_context_path()) else {
+        return Some(Reason::Missing);
+    };
+    let generated = metadata.modified().ok()?;
+    let edited = std::fs::metadata(config.cmake_lists_path())
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    (generated < edited).then_some(Reason::Stale)
+}
+
+/// Returns whether the `CMake` build should be configured, asking the user unless `--auto-configure` decides it.
+fn ask(context: &Context<'_>, command: &str, reason: &Reason) -> bool {
+    if context.options.auto_configure {
+        return true;
+    }
+    if context.options.dry_run || !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return false;
+    }
+    let path = context.config.cmake_context_path();
+    eprint!("`{path}` {reason}, but `xtask {command}` needs it. Run `xtask cmake build` now? [y/N] ");
+    std::io::stderr().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Configures the `CMake` build as `xtask cmake build` does, on behalf of the xtask command named `command`.
+fn run(context: &Context<'_>, command: &str) -> BoxResult<()> {
+    if context.options.verbosity >= Verbosity::Normal {
+        crate::process::eprintln(&format!("configuring the CMake build for `xtask {command}`"));
+    }
+    let mut args = pico_args::Arguments::from_vec(vec![OsString::from("build")]);
+    let mut cmake = Context::new(context.config, &mut args, vec![]);
+    cmake.registry = context.registry;
+    cmake.runner = context.runner;
+    cmake.options = context.options.clone();
+    cmake.options.timeout = context.config.xtask.timeout("cmake", Some("build"));
+    let status = crate::report::with_task("cmake", || crate::command::CMake.run(cmake))?;
+    if let Some(err) = status.and_then(|status| XtaskError::from_status("cmake build", status)) {
+        return Err(err.into());
+    }
+    Ok(())
+}
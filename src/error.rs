@@ -0,0 +1,146 @@
+use crate::BoxError;
+use camino::Utf8PathBuf;
+use std::{error::Error, fmt, process::ExitStatus, time::Duration};
+
+/// The exit codes of xtask's own errors, which are not passed through from child processes.
+const RESERVED: [i32; 5] = [2, 3, 4, 124, 127];
+
+/// The errors xtask distinguishes, each of which maps to its own process exit code.
+///
+/// | Variant              | Exit code                                           |
+/// |----------------------|-----------------------------------------------------|
+/// | `BadArguments`       | 2                                                   |
+/// | `MissingConfig`      | 3                                                   |
+/// | `ToolVersion`        | 4                                                   |
+/// | `ToolNotFound`       | 127                                                 |
+/// | `ChildFailed`        | the exit code of the child process, unless reserved |
+/// | `ChildKilled`        | 128 + the number of the signal                      |
+/// | `Interrupted`        | 128 + the number of the signal                      |
+/// | `Timeout`            | 124                                                 |
+/// | `PrerequisiteFailed` | the exit code of the source                         |
+///
+/// Any other error exits with code 1, as does a child process exiting with one of the codes reserved for xtask's own
+/// errors (2, 3, 4, 124 and 127), so that e.g. a tool exiting with code 3 is not mistaken for a missing configuration
+/// file.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub enum XtaskError {
+    /// The command line is invalid.
+    BadArguments { message: String, source: Option<BoxError> },
+    /// A configuration file required by the command does not exist.
+    MissingConfig { path: Utf8PathBuf, command: String },
+    /// A tool required by the command is not the expected version.
+    ToolVersion {
+        tool: String,
+        expected: String,
+        found: String,
+    },
+    /// A tool required by the command could not be found.
+    ToolNotFound { tool: String, source: std::io::Error },
+    /// A child process exited with a non-zero exit code.
+    ChildFailed { command: String, code: i32 },
+    /// A child process was terminated by a signal.
+    ChildKilled { command: String, signal: i32 },
+    /// xtask received a signal, which was forwarded to the running child processes.
+    Interrupted { signal: i32 },
+    /// A child process ran for longer than the timeout configured for the command and was killed.
+    Timeout { command: String, elapsed: Duration },
+    /// A prerequisite of the command failed with the error `source`, so the command was not run.
+    PrerequisiteFailed {
+        prerequisite: String,
+        command: String,
+        source: BoxError,
+    },
+}
+
+impl XtaskError {
+    /// Creates a [`XtaskError::BadArguments`] error without a source.
+    #[must_use]
+    pub fn bad_arguments(message: impl Into<String>) -> Self {
+        Self::BadArguments {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Returns the error describing how `command` failed, or `None` if `status` is successful.
+    #[must_use]
+    pub fn from_status(command: &str, status: ExitStatus) -> Option<Self> {
+        if status.success() {
+            return None;
+        }
+        let command = command.to_owned();
+        match (status.code(), crate::process::signal(status)) {
+            (_, Some(signal)) => Some(Self::ChildKilled { command, signal }),
+            (code, None) => Some(Self::ChildFailed {
+                command,
+                code: code.unwrap_or(1),
+            }),
+        }
+    }
+
+    /// Returns the process exit code documented for the error.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::BadArguments { .. } => 2,
+            Self::MissingConfig { .. } => 3,
+            Self::ToolVersion { .. } => 4,
+            Self::ToolNotFound { .. } => 127,
+            Self::ChildFailed { code, .. } if RESERVED.contains(code) => 1,
+            Self::ChildFailed { code, .. } => *code,
+            Self::ChildKilled { signal, .. } | Self::Interrupted { signal } => 128 + signal,
+            Self::Timeout { .. } => 124,
+            Self::PrerequisiteFailed { source, .. } => exit_code(&**source),
+        }
+    }
+}
+
+impl fmt::Display for XtaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadArguments { message, .. } => write!(f, "{message}"),
+            Self::MissingConfig { path, command } => {
+                write!(f, "configuration file `{path}` required by `xtask {command}` not found")
+            },
+            Self::ToolVersion { tool, expected, found } => {
+                write!(f, "`{tool}` has version {found} but {expected} is required")
+            },
+            Self::ToolNotFound { tool, .. } => write!(f, "tool `{tool}` not found"),
+            Self::ChildFailed { command, code } => write!(f, "`{command}` failed with exit code {code}"),
+            Self::ChildKilled { command, signal } => write!(f, "`{command}` was killed by signal {signal}"),
+            Self::Interrupted { signal } => write!(f, "interrupted by signal {signal}"),
+            Self::Timeout { command, elapsed } => {
+                write!(f, "`{command}` timed out after {:.1}s", elapsed.as_secs_f64())
+            },
+            Self::PrerequisiteFailed {
+                prerequisite, command, ..
+            } => write!(f, "prerequisite `{prerequisite}` of `xtask {command}` failed"),
+        }
+    }
+}
+
+impl Error for XtaskError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::BadArguments {
+                source: Some(source), ..
+            }
+            | Self::PrerequisiteFailed { source, .. } => Some(&**source),
+            Self::ToolNotFound { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the process exit code for `error`, as documented on [`XtaskError`].
+#[must_use]
+pub fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<XtaskError>() {
+        error.exit_code()
+    } else if error.is::<pico_args::Error>() {
+        2
+    } else {
+        1
+    }
+}
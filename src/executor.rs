@@ -0,0 +1,274 @@
+use crate::{
+    config::Config,
+    options::{Options, Verbosity},
+    registry::Registry,
+    BoxError,
+    BoxResult,
+};
+use std::{
+    ffi::OsString,
+    process::ExitStatus,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// An xtask command to be run by an [`Executor`].
+pub struct Invocation {
+    /// The command line without the leading `xtask`, as shown in output and reports.
+    pub label: String,
+    pub args: Vec<OsString>,
+}
+
+impl Invocation {
+    /// Creates an invocation from a whitespace-separated command line, e.g. `fmt -- --check`.
+    #[must_use]
+    pub fn parse(line: &str) -> Self {
+        Self {
+            label: line.to_owned(),
+            args: line.split_whitespace().map(OsString::from).collect(),
+        }
+    }
+}
+
+/// How an [`Invocation`] ended.
+pub enum Outcome {
+    /// The command ran to completion. The status is `None` if no process was spawned, e.g. with `--dry-run`.
+    Finished {
+        status: Option<ExitStatus>,
+        elapsed: Duration,
+    },
+    /// The command failed with an error before or while spawning its process.
+    Errored { error: BoxError, elapsed: Duration },
+    /// The command was not run because its inputs are unchanged since its last successful run.
+    Cached,
+    /// The command was not run because a prerequisite or, without `--keep-going`, an earlier command failed.
+    Skipped,
+}
+
+impl Outcome {
+    #[must_use]
+    pub fn failed(&self) -> bool {
+        match self {
+            Self::Finished { status, .. } => status.map_or(false, |status| !status.success()),
+            Self::Errored { .. } => true,
+            Self::Cached | Self::Skipped => false,
+        }
+    }
+
+    #[must_use]
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self {
+            Self::Finished { elapsed, .. } | Self::Errored { elapsed, .. } => Some(*elapsed),
+            Self::Cached | Self::Skipped => None,
+        }
+    }
+}
+
+pub struct Report {
+    pub label: String,
+    pub outcome: Outcome,
+}
+
+/// Runs xtask commands, up to `--jobs` of them concurrently.
+///
+/// The prerequisites declared by each command (see [`Task::dependencies`](crate::command::Task::dependencies)) are
+/// run first. Commands are ordered so that each one starts only after its prerequisites have finished, and a command
+/// needed by several others runs only once. When more than one job is allowed, the output of each command is prefixed
+/// with its label.
+pub struct Executor<'a> {
+    registry: &'a Registry,
+    config: &'a Config,
+    options: &'a Options,
+}
+
+/// An [`Invocation`] in an execution plan, together with the plan indices of its prerequisites.
+struct Node {
+    invocation: Invocation,
+    dependencies: Vec<usize>,
+}
+
+/// The progress of an execution plan, shared between workers.
+struct State {
+    started: Vec<bool>,
+    outcomes: Vec<Option<Outcome>>,
+    failed: bool,
+}
+
+impl<'a> Executor<'a> {
+    #[must_use]
+    pub fn new(registry: &'a Registry, config: &'a Config, options: &'a Options) -> Self {
+        Self {
+            registry,
+            config,
+            options,
+        }
+    }
+
+    /// Runs `invocations` and their prerequisites and returns a report for each command run.
+    ///
+    /// Reports are in execution plan order, i.e. every prerequisite is reported before the commands which need it.
+    /// A command is skipped if one of its prerequisites failed or was skipped. Unless `keep_going` is set, no further
+    /// commands are started once one has failed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the prerequisites of the commands form a cycle.
+    pub fn run(&self, invocations: Vec<Invocation>, keep_going: bool) -> BoxResult<Vec<Report>> {
+        let plan = self.plan(invocations)?;
+        let jobs = self.options.jobs.min(plan.len()).max(1);
+        let state = Mutex::new(State {
+            started: vec![false; plan.len()],
+            outcomes: (0 .. plan.len()).map(|_| None).collect(),
+            failed: false,
+        });
+        let ready = Condvar::new();
+        let worker = || {
+            let Ok(mut guard) = state.lock() else {
+                return;
+            };
+            loop {
+                let next = (0 .. plan.len()).find(|&index| {
+                    !guard.started[index]
+                        && plan[index]
+                            .dependencies
+                            .iter()
+                            .all(|&dependency| guard.outcomes[dependency].is_some())
+                });
+                let Some(index) = next else {
+                    if guard.started.iter().all(|&started| started) {
+                        break;
+                    }
+                    let Ok(next_guard) = ready.wait(guard) else {
+                        return;
+                    };
+                    guard = next_guard;
+                    continue;
+                };
+                guard.started[index] = true;
+                let node = &plan[index];
+                let unsatisfied = node.dependencies.iter().any(|&dependency| {
+                    guard.outcomes[dependency]
+                        .as_ref()
+                        .map_or(true, |outcome| outcome.failed() || matches!(outcome, Outcome::Skipped))
+                });
+                let outcome = if unsatisfied || (guard.failed && !keep_going) {
+                    Outcome::Skipped
+                } else {
+                    drop(guard);
+                    let outcome = if jobs > 1 {
+                        let prefix = format!("[{}] ", node.invocation.label);
+                        crate::process::with_prefix(&prefix, || self.invoke(&node.invocation))
+                    } else {
+                        self.invoke(&node.invocation)
+                    };
+                    let Ok(next_guard) = state.lock() else {
+                        return;
+                    };
+                    guard = next_guard;
+                    outcome
+                };
+                guard.failed |= outcome.failed();
+                guard.outcomes[index] = Some(outcome);
+                ready.notify_all();
+            }
+        };
+        if jobs == 1 {
+            worker();
+        } else {
+            std::thread::scope(|scope| {
+                for _ in 0 .. jobs {
+                    scope.spawn(worker);
+                }
+            });
+        }
+        let outcomes = state.into_inner().map(|state| state.outcomes).unwrap_or_default();
+        Ok(plan
+            .into_iter()
+            .zip(outcomes)
+            .map(|(node, outcome)| Report {
+                label: node.invocation.label,
+                outcome: outcome.unwrap_or(Outcome::Skipped),
+            })
+            .collect())
+    }
+
+    /// Expands `invocations` with their prerequisites into a topologically ordered, deduplicated plan.
+    fn plan(&self, invocations: Vec<Invocation>) -> BoxResult<Vec<Node>> {
+        let mut plan = Vec::new();
+        let mut path = Vec::new();
+        for invocation in invocations {
+            self.visit(invocation, &mut plan, &mut path)?;
+        }
+        Ok(plan)
+    }
+
+    fn visit(&self, invocation: Invocation, plan: &mut Vec<Node>, path: &mut Vec<String>) -> BoxResult<usize> {
+        if let Some(index) = plan.iter().position(|node| node.invocation.label == invocation.label) {
+            return Ok(index);
+        }
+        if path.contains(&invocation.label) {
+            path.push(invocation.label);
+            let cycle = path.iter().map(|label| format!("`{label}`")).collect::<Vec<_>>();
+            return Err(format!("xtask commands depend on each other: {}", cycle.join(" -> ")).into());
+        }
+        path.push(invocation.label.clone());
+        let mut dependencies = Vec::new();
+        for dependency in self.dependencies(&invocation) {
+            dependencies.push(self.visit(dependency, plan, path)?);
+        }
+        path.pop();
+        plan.push(Node {
+            invocation,
+            dependencies,
+        });
+        Ok(plan.len() - 1)
+    }
+
+    fn dependencies(&self, invocation: &Invocation) -> Vec<Invocation> {
+        let args = invocation.args.split(|arg| arg == "--").next().unwrap_or_default();
+        let Some((name, args)) = args.split_first() else {
+            return Vec::new();
+        };
+        name.to_str()
+            .and_then(|name| self.registry.get(name))
+            .map_or_else(Vec::new, |task| task.dependencies(args))
+    }
+
+    fn invoke(&self, invocation: &Invocation) -> Outcome {
+        let start = Instant::now();
+        let fingerprint = match self.registry.fingerprint(self.config, self.options, &invocation.args) {
+            Ok(fingerprint) => fingerprint,
+            Err(error) => {
+                crate::process::eprintln(&format!("error: {error}"));
+                let elapsed = start.elapsed();
+                return Outcome::Errored { error, elapsed };
+            },
+        };
+        if fingerprint
+            .as_ref()
+            .map_or(false, |fingerprint| fingerprint.is_fresh(self.config))
+        {
+            if Verbosity::current() > Verbosity::Quiet {
+                crate::process::eprintln(&format!("[xtask] `{}` is up to date (cached)", invocation.label));
+            }
+            return Outcome::Cached;
+        }
+        if Verbosity::current() > Verbosity::Quiet {
+            crate::process::eprintln(&format!("[xtask] running `{}`", invocation.label));
+        }
+        let result = self.registry.invoke(self.config, self.options, invocation.args.clone());
+        let elapsed = start.elapsed();
+        match result {
+            Ok(status) => {
+                if let Some(fingerprint) = &fingerprint {
+                    fingerprint.update(self.config, status);
+                }
+                Outcome::Finished { status, elapsed }
+            },
+            Err(error) => {
+                crate::process::eprintln(&format!("error: {error}"));
+                Outcome::Errored { error, elapsed }
+            },
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::BoxResult;
+use crate::{error::XtaskError, BoxResult};
 use std::process::ExitStatus;
 
 /// # Errors
@@ -13,22 +13,36 @@ pub fn help(args: &mut pico_args::Arguments, help: &str) -> BoxResult<bool> {
     Ok(false)
 }
 
+/// Exits the process if `result` is an error, after printing the error and its causes to stderr.
+///
+/// The exit code is determined by [`error::exit_code`](crate::error::exit_code).
 pub fn result<T>(result: BoxResult<T>) {
     if let Err(err) = result {
-        println!("error: {err}");
-        let code = 1;
+        eprintln!("error: {err}");
+        let mut source = err.source();
+        while let Some(cause) = source {
+            eprintln!("caused by: {cause}");
+            source = cause.source();
+        }
+        let logs = crate::logs::failed();
+        if !logs.is_empty() {
+            eprintln!("logs of the failed commands:");
+            for log in logs {
+                eprintln!("    {log}");
+            }
+        }
+        let code = crate::error::exit_code(&*err);
         std::process::exit(code);
     }
 }
 
+/// Exits the process if `result` is an error or the exit status of a failed `subcommand`.
 pub fn subcommand_result(subcommand: &str, result: BoxResult<Option<ExitStatus>>) {
     match result {
         Ok(None) => {},
         Ok(Some(status)) => {
-            if !status.success() {
-                println!("error: subcommand `{subcommand}` failed with non-zero exit code");
-                let code = status.code().unwrap_or(1);
-                std::process::exit(code);
+            if let Some(err) = XtaskError::from_status(subcommand, status) {
+                crate::handler::result::<()>(Err(err.into()));
             }
         },
         result => crate::handler::result(result),
@@ -39,15 +53,27 @@ pub fn subcommand_result(subcommand: &str, result: BoxResult<Option<ExitStatus>>
 ///
 /// Will return `Err` if unused arguments remain in `args`.
 pub fn unused(args: &pico_args::Arguments) -> BoxResult<()> {
+    unrecognized(args, &[])
+}
+
+/// # Errors
+///
+/// Will return `Err` if unused arguments remain in `args`. If one of them looks like a misspelling of an argument in
+/// `known`, the error suggests the correct spelling.
+pub fn unrecognized(args: &pico_args::Arguments, known: &[&str]) -> BoxResult<()> {
     use std::borrow::Borrow;
     let unused = args.clone().finish();
     if unused.is_empty() {
         return Ok(());
     }
     let mut message = String::new();
-    for str in unused {
+    for str in &unused {
         message.push(' ');
         message.push_str(str.to_string_lossy().borrow());
     }
-    Err(format!("unrecognized arguments `{message}`").into())
+    let suggestion = unused
+        .iter()
+        .find_map(|str| crate::suggest::closest(&str.to_string_lossy(), known.iter().copied()))
+        .map_or_else(String::new, |known| format!("; did you mean `{known}`?"));
+    Err(XtaskError::bad_arguments(format!("unrecognized arguments `{message}`{suggestion}")).into())
 }
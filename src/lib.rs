@@ -2,11 +2,27 @@
 #![deny(clippy::pedantic)]
 #![deny(unsafe_code)]
 
+pub mod cache;
 pub mod command;
+pub mod completions;
 pub mod config;
+pub mod configure;
 // pub mod detection;
+pub mod error;
+pub mod executor;
 pub mod handler;
+pub mod logs;
+pub mod options;
+pub mod packages;
+pub mod process;
 // pub mod install;
+pub mod registry;
+pub mod report;
+pub mod runner;
+pub mod signal;
+pub mod sources;
+pub mod spec;
+pub mod suggest;
 // pub mod validation;
 pub mod workspace;
 
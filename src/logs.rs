@@ -0,0 +1,93 @@
+use crate::BoxResult;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::{
+    fs::File,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+/// A log file written for a spawned process.
+struct Log {
+    path: Utf8PathBuf,
+    failed: bool,
+}
+
+static LOGS: Mutex<Vec<Log>> = Mutex::new(Vec::new());
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates the log file for the next process spawned by the current command, in `dir`.
+///
+/// Files are numbered in the order they are created and named after the xtask command, e.g. `03-clippy.log`.
+///
+/// # Errors
+///
+/// Will return `Err` if `dir` or the log file cannot be created.
+pub(crate) fn create(dir: &Utf8Path) -> BoxResult<(Utf8PathBuf, File)> {
+    std::fs::create_dir_all(dir).map_err(|err| format!("failed to create log directory `{dir}`: {err}"))?;
+    let index = COUNT.fetch_add(1, Ordering::SeqCst) + 1;
+    let task = crate::report::task().unwrap_or_else(|| String::from("xtask"));
+    let path = dir.join(format!("{index:02}-{task}.log"));
+    let file = File::create(&path).map_err(|err| format!("failed to create log file `{path}`: {err}"))?;
+    Ok((path, file))
+}
+
+/// Records that the process logged to `path` has exited, and whether it failed.
+pub(crate) fn finish(path: Utf8PathBuf, failed: bool) {
+    if let Ok(mut logs) = LOGS.lock() {
+        logs.push(Log { path, failed });
+    }
+}
+
+/// Returns the log files of the processes which failed, in the order they were created.
+#[must_use]
+pub fn failed() -> Vec<Utf8PathBuf> {
+    let Ok(logs) = LOGS.lock() else {
+        return Vec::new();
+    };
+    let mut failed = logs
+        .iter()
+        .filter(|log| log.failed)
+        .map(|log| log.path.clone())
+        .collect::<Vec<_>>();
+    failed.sort();
+    failed
+}
+
+/// Returns the default log directory below `target_dir`, named after the current UTC time, e.g.
+/// `target/xtask/logs/20231018T154502Z`.
+#[must_use]
+pub fn default_dir(target_dir: &Utf8Path) -> Utf8PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let days = i64::try_from(secs / 86_400).unwrap_or_default();
+    let secs = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let timestamp = format!("{year:04}{month:02}{day:02}T{hour:02}{minute:02}{second:02}Z");
+    target_dir.join("xtask/logs").join(timestamp)
+}
+
+/// Converts a number of days since the Unix epoch into a proleptic Gregorian `(year, month, day)`.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,12 @@
+#![deny(clippy::all)]
+#![deny(clippy::pedantic)]
+#![deny(unsafe_code)]
+
+use cxx_auto_xtask::{handler, registry::Registry};
+
+fn main() {
+    let args = pico_args::Arguments::from_env();
+    let registry = Registry::default();
+    let result = registry.dispatch(args);
+    handler::subcommand_result("xtask", result);
+}
@@ -0,0 +1,194 @@
+use crate::{
+    error::XtaskError,
+    spec::{Flag, Matches},
+    BoxResult,
+};
+use camino::Utf8PathBuf;
+use std::{
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+
+/// How much output xtask itself produces, independently of the output of the tools it runs.
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors are reported (`-q`).
+    Quiet,
+    #[default]
+    Normal,
+    /// Every spawned command is echoed before it runs (`-v`).
+    Verbose,
+    /// Resolved configuration values are also reported (`-vv`).
+    Debug,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+impl Verbosity {
+    /// Returns the verbosity of the current process, as set by [`Verbosity::install`].
+    #[must_use]
+    pub fn current() -> Self {
+        match VERBOSITY.load(Ordering::Relaxed) {
+            0 => Self::Quiet,
+            1 => Self::Normal,
+            2 => Self::Verbose,
+            _ => Self::Debug,
+        }
+    }
+
+    /// Makes this the verbosity of the current process.
+    pub fn install(self) {
+        VERBOSITY.store(self as u8, Ordering::Relaxed);
+    }
+}
+
+/// Options which apply to every command, given anywhere on the command line before `--`.
+#[allow(clippy::struct_excessive_bools)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct Options {
+    /// Print the commands which would be spawned instead of running them.
+    pub dry_run: bool,
+    pub verbosity: Verbosity,
+    /// The maximum number of commands run concurrently by commands which run other commands, e.g. `xtask ci`.
+    pub jobs: usize,
+    /// Where to write a JSON record of every process spawned by the command.
+    pub report_json: Option<Utf8PathBuf>,
+    /// Run commands even if the cache considers them up to date.
+    pub no_cache: bool,
+    /// The workspace manifest to load, instead of the one found from the current directory.
+    pub manifest_path: Option<Utf8PathBuf>,
+    /// The directory to find the workspace from, instead of the current directory (`-C`).
+    pub directory: Option<Utf8PathBuf>,
+    /// Tee the output of every spawned process into a log file.
+    pub log: bool,
+    /// The directory to write log files to. Defaults to `target/xtask/logs/<timestamp>/` once the workspace is loaded.
+    pub log_dir: Option<Utf8PathBuf>,
+    /// The `CMake` build directory to use instead of the configured default: the name of a configured build directory,
+    /// or a path relative to the workspace root.
+    pub build_dir: Option<String>,
+    /// Configure the `CMake` build without asking when a command needs its missing or stale context.
+    pub auto_configure: bool,
+    /// The wall-clock time after which tools run by the current command are killed.
+    ///
+    /// This is not a command line flag but is set for each command from the `[workspace.metadata.xtask.timeouts]`
+    /// table.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            verbosity: Verbosity::default(),
+            jobs: 1,
+            report_json: None,
+            no_cache: false,
+            manifest_path: None,
+            directory: None,
+            log: false,
+            log_dir: None,
+            build_dir: None,
+            auto_configure: false,
+            timeout: None,
+        }
+    }
+}
+
+impl Options {
+    pub const FLAGS: &'static [Flag] = &[
+        Flag::new(
+            "--dry-run",
+            "Print the commands which would be run without running them",
+        ),
+        Flag::new(
+            "--verbose",
+            "Echo commands before running them (-vv also prints resolved configuration)",
+        )
+        .short("-v"),
+        Flag::new("--quiet", "Only print errors from xtask itself").short("-q"),
+        Flag::new(
+            "--jobs",
+            "Number of independent commands to run concurrently [default: 1]",
+        )
+        .short("-j")
+        .value("N"),
+        Flag::new("--report-json", "Write a JSON record of every process run to <PATH>").value("PATH"),
+        Flag::new(
+            "--no-cache",
+            "Run commands even if their inputs are unchanged since their last successful run",
+        ),
+        Flag::new(
+            "--manifest-path",
+            "Path to the Cargo.toml of the workspace to operate on",
+        )
+        .value("PATH"),
+        Flag::new(
+            "--directory",
+            "Operate on the workspace containing <DIR> instead of the current directory",
+        )
+        .short("-C")
+        .value("DIR"),
+        Flag::new(
+            "--log",
+            "Also write the output of every process run to a log file in target/xtask/logs/<timestamp>/",
+        ),
+        Flag::new("--log-dir", "Like `--log`, but write the log files to <DIR>").value("DIR"),
+        Flag::new(
+            "--build-dir",
+            "Use the CMake build directory named <DIR> in the configuration, or at the path <DIR>",
+        )
+        .value("DIR"),
+        Flag::new(
+            "--auto-configure",
+            "Run `cmake build` without asking when the build directory's cxx-auto-context.json is missing or stale",
+        ),
+    ];
+
+    /// Extracts the global options from `args`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails
+    /// - Both `--quiet` and `--verbose` are given
+    /// - The `--jobs` value is not a positive integer
+    pub fn parse(args: &mut pico_args::Arguments) -> BoxResult<Self> {
+        let matches = Matches::parse(Self::FLAGS, args)?;
+        let verbosity = match (matches.contains("--quiet"), matches.count("--verbose")) {
+            (true, 0) => Verbosity::Quiet,
+            (true, _) => {
+                let message = "`--quiet` and `--verbose` cannot be used together";
+                return Err(XtaskError::bad_arguments(message).into());
+            },
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        };
+        let jobs = match matches.value("--jobs") {
+            Some(jobs) => match jobs.parse() {
+                Ok(jobs) if jobs > 0 => jobs,
+                _ => {
+                    let message = format!("invalid `--jobs` value `{jobs}`; expected a positive integer");
+                    return Err(XtaskError::bad_arguments(message).into());
+                },
+            },
+            None => 1,
+        };
+        Ok(Self {
+            dry_run: matches.contains("--dry-run"),
+            verbosity,
+            jobs,
+            report_json: matches.value("--report-json").map(Utf8PathBuf::from),
+            no_cache: matches.contains("--no-cache"),
+            manifest_path: matches.value("--manifest-path").map(Utf8PathBuf::from),
+            directory: matches.value("--directory").map(Utf8PathBuf::from),
+            log: matches.contains("--log") || matches.contains("--log-dir"),
+            log_dir: matches.value("--log-dir").map(Utf8PathBuf::from),
+            build_dir: matches.value("--build-dir").map(String::from),
+            auto_configure: matches.contains("--auto-configure"),
+            timeout: None,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    config::Config,
+    error::XtaskError,
+    spec::{Flag, Matches},
+    BoxResult,
+};
+use std::ffi::OsString;
+
+/// The package selection flags accepted by commands which operate on cargo packages.
+pub const FLAGS: [Flag; 3] = [
+    Flag::new("--package", "Package to operate on (may be repeated)")
+        .short("-p")
+        .value("SPEC"),
+    Flag::new("--workspace", "Operate on all workspace members"),
+    Flag::new("--exclude", "Package to leave out of the selection (may be repeated)").value("SPEC"),
+];
+
+/// Resolves the packages that `command` should operate on.
+///
+/// Packages given with `--package` take precedence. Otherwise, unless `--workspace` is given, the `packages` array from
+/// the command's `[workspace.metadata.xtask.<command>]` table is used, falling back to all workspace members. Packages
+/// given with `--exclude` are then removed from the selection.
+///
+/// # Errors
+///
+/// Will return `Err` if the selection is empty after applying `--exclude`.
+pub fn select(config: &Config, command: &str, matches: &Matches) -> BoxResult<Vec<String>> {
+    let mut packages = if !matches.values("--package").is_empty() {
+        matches.values("--package").to_vec()
+    } else if matches.contains("--workspace") {
+        members(config)
+    } else if let Some(packages) = config.xtask.packages(command) {
+        packages.to_vec()
+    } else {
+        members(config)
+    };
+    let excluded = matches.values("--exclude");
+    packages.retain(|package| !excluded.contains(package));
+    if packages.is_empty() {
+        return Err(XtaskError::bad_arguments(format!("no packages selected for `xtask {command}`")).into());
+    }
+    Ok(packages)
+}
+
+/// Returns the package selection flags in `args`, the arguments of a command with [`FLAGS`], to pass them on to
+/// another command.
+///
+/// # Errors
+///
+/// Will return `Err` if a flag value is not valid UTF-8.
+pub fn forward(args: &[OsString]) -> BoxResult<Vec<String>> {
+    let matches = Matches::parse(&FLAGS, &mut pico_args::Arguments::from_vec(args.to_vec()))?;
+    let mut forwarded = Vec::new();
+    for flag in &FLAGS {
+        for value in matches.values(flag.long) {
+            forwarded.push(flag.long.to_owned());
+            if flag.value.is_some() {
+                forwarded.push(value.clone());
+            }
+        }
+    }
+    Ok(forwarded)
+}
+
+fn members(config: &Config) -> Vec<String> {
+    let metadata = &config.cargo_metadata;
+    metadata
+        .workspace_packages()
+        .into_iter()
+        .map(|package| package.name.clone())
+        .collect()
+}
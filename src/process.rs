@@ -0,0 +1,272 @@
+use crate::{
+    error::XtaskError,
+    options::{Options, Verbosity},
+    signal::Group,
+    BoxError,
+    BoxResult,
+};
+use std::{
+    cell::RefCell,
+    ffi::OsStr,
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+thread_local! {
+    static PREFIX: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Runs `f` with every line of output from commands spawned on this thread prefixed with `prefix`.
+///
+/// This is used to tell apart the output of commands which run concurrently.
+pub fn with_prefix<T>(prefix: &str, f: impl FnOnce() -> T) -> T {
+    let previous = PREFIX.with(|cell| cell.replace(Some(prefix.to_owned())));
+    let result = f();
+    PREFIX.with(|cell| cell.replace(previous));
+    result
+}
+
+fn prefix() -> Option<String> {
+    PREFIX.with(|cell| cell.borrow().clone())
+}
+
+/// Prints `line` to stdout, prefixed as configured by [`with_prefix`].
+pub fn println(line: &str) {
+    println!("{}{line}", prefix().unwrap_or_default());
+}
+
+/// Prints `line` to stderr, prefixed as configured by [`with_prefix`].
+pub fn eprintln(line: &str) {
+    eprintln!("{}{line}", prefix().unwrap_or_default());
+}
+
+/// Runs `cmd` to completion and returns its exit status.
+///
+/// With `--dry-run`, the command is only printed and `Ok(None)` is returned. With `--verbose`, the command is echoed to
+/// stderr before it runs. Within [`with_prefix`], the output of the command is captured and forwarded line by line
+/// with the prefix prepended. With `--log`, the output is also written to a log file. With `--report-json`, a
+/// [`Record`](crate::report::Record) of the process is kept.
+///
+/// # Errors
+///
+/// Will return `Err` under the following circumstances:
+/// - The command process fails to start, e.g. with [`XtaskError::ToolNotFound`] if the program does not exist
+/// - xtask is interrupted by `SIGINT` or `SIGTERM` ([`XtaskError::Interrupted`]), in which case the signal is
+///   forwarded to the process group of the command, which is waited for
+/// - The command runs for longer than [`Options::timeout`] ([`XtaskError::Timeout`]), in which case its process group
+///   is killed
+/// - The log file for the command cannot be created
+pub fn status(options: &Options, cmd: &mut Command) -> BoxResult<Option<ExitStatus>> {
+    if options.dry_run {
+        println(&display(cmd));
+        return Ok(None);
+    }
+    if options.verbosity >= Verbosity::Verbose {
+        eprintln(&format!("[xtask] $ {}", display(cmd)));
+    }
+    if let Some(signal) = crate::signal::received() {
+        return Err(XtaskError::Interrupted { signal }.into());
+    }
+    let started = SystemTime::now();
+    let start = Instant::now();
+    let prefix = prefix();
+    let log = match &options.log_dir {
+        Some(dir) => {
+            let (path, mut file) = crate::logs::create(dir)?;
+            writeln!(file, "$ {}", display(cmd))?;
+            Some((path, Mutex::new(file)))
+        },
+        None => None,
+    };
+    if prefix.is_some() || log.is_some() {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let terminal = crate::signal::isolate(cmd);
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            if terminal {
+                crate::signal::release_terminal();
+            }
+            let err = spawn_error(cmd, err);
+            if let Some((path, file)) = log {
+                if let Ok(mut file) = file.into_inner() {
+                    writeln!(file, "xtask: {err}").ok();
+                }
+                crate::logs::finish(path, true);
+            }
+            if options.report_json.is_some() {
+                crate::report::record(cmd, started, start.elapsed(), Err(&err));
+            }
+            return Err(err);
+        },
+    };
+    let group = Group::new(&child, terminal);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let prefix = prefix.as_deref().unwrap_or_default();
+    let log_file = log.as_ref().map(|(_, file)| file);
+    let (status, interrupted) = std::thread::scope(|scope| {
+        if let Some(stdout) = stdout {
+            scope.spawn(|| forward(stdout, prefix, || std::io::stdout().lock(), log_file));
+        }
+        if let Some(stderr) = stderr {
+            scope.spawn(|| forward(stderr, prefix, || std::io::stderr().lock(), log_file));
+        }
+        let status = match options.timeout {
+            Some(timeout) => wait_timeout(&mut child, &group, timeout),
+            None => group.wait(&mut child).map(|status| (status, false)),
+        };
+        (status, group.finish())
+    });
+    if let Some((path, _)) = log {
+        let failed = status.as_ref().map_or(true, |(status, timed_out)| {
+            !status.success() || *timed_out || interrupted.is_some()
+        });
+        crate::logs::finish(path, failed);
+    }
+    let (status, timed_out) = status?;
+    let elapsed = start.elapsed();
+    if options.report_json.is_some() {
+        crate::report::record(cmd, started, elapsed, Ok(status));
+    }
+    if let Some(signal) = interrupted {
+        return Err(XtaskError::Interrupted { signal }.into());
+    }
+    if timed_out {
+        let command = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(OsStr::to_string_lossy)
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Err(XtaskError::Timeout { command, elapsed }.into());
+    }
+    Ok(Some(status))
+}
+
+/// Waits for `child` to exit for at most `timeout`, after which its process group is killed.
+///
+/// Returns the exit status of the child and whether it was killed.
+fn wait_timeout(child: &mut Child, group: &Group, timeout: Duration) -> std::io::Result<(ExitStatus, bool)> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = group.try_wait(child)? {
+            return Ok((status, false));
+        }
+        if Instant::now() >= deadline {
+            group.kill(child)?;
+            return Ok((group.wait(child)?, true));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Converts the error from spawning `cmd` into an [`XtaskError::ToolNotFound`] if the program does not exist.
+fn spawn_error(cmd: &Command, err: std::io::Error) -> BoxError {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        let tool = cmd.get_program().to_string_lossy().into_owned();
+        XtaskError::ToolNotFound { tool, source: err }.into()
+    } else {
+        err.into()
+    }
+}
+
+/// Returns the signal which terminated the process with `status`, if any.
+#[cfg(unix)]
+pub(crate) fn signal(status: ExitStatus) -> Option<i32> {
+    std::os::unix::process::ExitStatusExt::signal(&status)
+}
+
+/// Returns the signal which terminated the process with `status`, if any.
+#[cfg(not(unix))]
+pub(crate) fn signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Copies `pipe` line by line to the writer returned by `output`, prefixing each line with `prefix`, and to `log`.
+///
+/// The writer is requested anew for every line so that lines from concurrent commands are not interleaved.
+fn forward<W: Write>(pipe: impl Read, prefix: &str, output: impl Fn() -> W, log: Option<&Mutex<File>>) {
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        if let Some(Ok(mut log)) = log.map(Mutex::lock) {
+            log.write_all(&line).ok();
+        }
+        let mut output = output();
+        if output
+            .write_all(prefix.as_bytes())
+            .and_then(|()| output.write_all(&line))
+            .is_err()
+        {
+            break;
+        }
+        line.clear();
+    }
+}
+
+/// Renders `cmd` as a shell command line, including its working directory and environment overrides.
+///
+/// The program is resolved against `PATH` where possible and every word is shell-quoted.
+#[must_use]
+pub fn display(cmd: &Command) -> String {
+    let mut words = vec![];
+    if let Some(dir) = cmd.get_current_dir() {
+        words.push(String::from("cd"));
+        words.push(quote(dir.as_os_str()));
+        words.push(String::from("&&"));
+    }
+    let mut envs = cmd.get_envs().peekable();
+    if envs.peek().is_some() {
+        words.push(String::from("env"));
+        for (key, value) in envs {
+            if let Some(value) = value {
+                let mut assignment = key.to_os_string();
+                assignment.push("=");
+                assignment.push(value);
+                words.push(quote(&assignment));
+            } else {
+                words.push(String::from("-u"));
+                words.push(quote(key));
+            }
+        }
+    }
+    let program = resolve(cmd);
+    words.push(quote(program.as_os_str()));
+    words.extend(cmd.get_args().map(quote));
+    words.join(" ")
+}
+
+/// Resolves the program of `cmd` to the executable that would be spawned, searching `PATH` for bare names.
+fn resolve(cmd: &Command) -> PathBuf {
+    let program = Path::new(cmd.get_program());
+    if program.components().count() > 1 {
+        return program.to_path_buf();
+    }
+    let path = cmd
+        .get_envs()
+        .find_map(|(key, value)| (key == "PATH").then_some(value))
+        .map_or_else(|| std::env::var_os("PATH"), |value| value.map(OsStr::to_os_string));
+    path.iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| program.to_path_buf())
+}
+
+/// Quotes `word` for a POSIX shell if it contains any characters which are not safe to leave unquoted.
+fn quote(word: &OsStr) -> String {
+    let word = word.to_string_lossy();
+    let safe = |char: char| char.is_ascii_alphanumeric() || "_-+=@%:,./".contains(char);
+    if !word.is_empty() && word.chars().all(safe) {
+        return word.into_owned();
+    }
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
@@ -0,0 +1,314 @@
+use crate::{
+    cache::Fingerprint,
+    command::{self, Context, Task},
+    config::Config,
+    error::XtaskError,
+    executor::{Executor, Outcome},
+    options::{Options, Verbosity},
+    runner::{ProcessRunner, Runner},
+    spec::Flag,
+    BoxError,
+    BoxResult,
+};
+use std::{ffi::OsString, process::ExitStatus, sync::Arc};
+
+/// The set of commands known to the `xtask` binary.
+///
+/// [`Registry::default`] registers all of the built-in commands. Additional commands can be added with
+/// [`Registry::register`], which replaces any previously registered command of the same name.
+///
+/// Processes spawned by the commands are run by a [`ProcessRunner`] unless another [`Runner`] is set with
+/// [`Registry::runner`].
+pub struct Registry {
+    tasks: Vec<Box<dyn Task>>,
+    runner: Arc<dyn Runner>,
+}
+
+impl Registry {
+    /// Creates a registry without any commands.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            tasks: Vec::new(),
+            runner: Arc::new(ProcessRunner),
+        }
+    }
+
+    /// Sets the [`Runner`] for the processes spawned by commands run through the registry.
+    pub fn runner(&mut self, runner: Arc<dyn Runner>) -> &mut Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Registers `task`, replacing any command previously registered under the same name.
+    pub fn register<T: Task + 'static>(&mut self, task: T) -> &mut Self {
+        let task = Box::new(task);
+        if let Some(slot) = self.tasks.iter_mut().find(|slot| slot.spec().name == task.spec().name) {
+            *slot = task;
+        } else {
+            self.tasks.push(task);
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Task> {
+        self.tasks
+            .iter()
+            .find(|task| task.spec().name == name)
+            .map(AsRef::as_ref)
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = &dyn Task> {
+        self.tasks.iter().map(AsRef::as_ref)
+    }
+
+    #[must_use]
+    pub fn help(&self) -> String {
+        let mut help = String::from("xtask\n\nUSAGE:\nxtask [FLAGS] [COMMAND]\n\nFLAGS:\n");
+        crate::spec::write_row(&mut help, "", "-h, --help", "Prints help information");
+        for flag in Options::FLAGS {
+            crate::spec::write_row(&mut help, "", &flag.label(), flag.about);
+        }
+        crate::spec::write_row(
+            &mut help,
+            "",
+            "-- '...'",
+            "Extra arguments to pass to the command's tool",
+        );
+        help.push_str("\nCOMMANDS:\n");
+        for task in self.tasks() {
+            let spec = task.spec();
+            crate::spec::write_row(&mut help, "    ", spec.name, spec.about);
+        }
+        help.truncate(help.trim_end().len());
+        help
+    }
+
+    /// Looks up the command named by the first free argument in `args` and runs it after its prerequisites.
+    ///
+    /// Arguments following `--` are split off and passed through to the command as tool arguments. The global
+    /// [`Options`] may appear anywhere before `--`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - The command is not registered
+    /// - Loading the [`Config`] fails, unless the command runs without a workspace (see [`Task::run_standalone`])
+    /// - Any of the command's prerequisites fails
+    /// - Running the command fails
+    /// - Writing the `--report-json` file fails
+    pub fn dispatch(&self, args: pico_args::Arguments) -> BoxResult<Option<ExitStatus>> {
+        let (mut args, tool_args) = split_tool_args(args);
+        let options = Options::parse(&mut args)?;
+        options.verbosity.install();
+
+        let result = self.dispatch_with(args, tool_args, &options);
+        let written = options.report_json.as_deref().map_or(Ok(()), crate::report::write);
+        let status = result?;
+        written?;
+        Ok(status)
+    }
+
+    fn dispatch_with(
+        &self,
+        mut args: pico_args::Arguments,
+        tool_args: Vec<OsString>,
+        options: &Options,
+    ) -> BoxResult<Option<ExitStatus>> {
+        let Some(name) = args.subcommand()? else {
+            let help = self.help();
+            if crate::handler::help(&mut args, &help)? {
+                return Ok(None);
+            }
+            let mut known = vec!["-h", "--help"];
+            known.extend(Options::FLAGS.iter().flat_map(Flag::names));
+            crate::handler::unrecognized(&args, &known)?;
+            println!("{help}\n");
+            return Ok(None);
+        };
+
+        let task = self.lookup(&name)?;
+
+        if crate::handler::help(&mut args, &task.spec().help())? {
+            return Ok(None);
+        }
+
+        if let Some(result) = task.run_standalone(self, &mut args, &tool_args) {
+            return result;
+        }
+
+        let config = Config::load_from(options.directory.as_deref(), options.manifest_path.as_deref())?
+            .with_build_dir(options.build_dir.clone())?;
+        let mut options = options.clone();
+        if options.log && options.log_dir.is_none() {
+            options.log_dir = Some(crate::logs::default_dir(&config.cargo_metadata.target_directory));
+        }
+        let options = &options;
+        if options.verbosity >= Verbosity::Debug {
+            config.report(&name);
+        }
+
+        let prerequisites = task.dependencies(&args.clone().finish());
+        if !prerequisites.is_empty() {
+            // Reject invalid arguments before running the prerequisites rather than after.
+            task.spec().parse(&mut args.clone())?;
+            let reports = Executor::new(self, &config, options).run(prerequisites, false)?;
+            if let Some(report) = reports.into_iter().find(|report| report.outcome.failed()) {
+                let source: BoxError = match report.outcome {
+                    Outcome::Finished {
+                        status: Some(status), ..
+                    } => match XtaskError::from_status(&report.label, status) {
+                        Some(err) => err.into(),
+                        None => format!("`{}` failed", report.label).into(),
+                    },
+                    Outcome::Errored { error, .. } => error,
+                    _ => format!("`{}` failed", report.label).into(),
+                };
+                let command = match first_free(&args) {
+                    Some(subcommand) if task.spec().subcommand(&subcommand).is_some() => format!("{name} {subcommand}"),
+                    _ => name,
+                };
+                return Err(XtaskError::PrerequisiteFailed {
+                    prerequisite: report.label,
+                    command,
+                    source,
+                }
+                .into());
+            }
+        }
+
+        let mut command = vec![OsString::from(&name)];
+        command.extend(args.clone().finish());
+        if !tool_args.is_empty() {
+            command.push("--".into());
+            command.extend(tool_args.iter().cloned());
+        }
+        let fingerprint = self.fingerprint(&config, options, &command)?;
+        if let Some(fingerprint) = &fingerprint {
+            if fingerprint.is_fresh(&config) {
+                if options.verbosity > Verbosity::Quiet {
+                    eprintln!("[xtask] `{}` is up to date (cached)", fingerprint.key());
+                }
+                return Ok(None);
+            }
+        }
+
+        let timeout = config.xtask.timeout(&name, first_free(&args).as_deref());
+        let mut context = Context::new(&config, &mut args, tool_args);
+        context.registry = Some(self);
+        context.runner = &*self.runner;
+        context.options = options.clone();
+        context.options.timeout = timeout;
+        let status = crate::report::with_task(&name, || task.run(context))?;
+        if let Some(fingerprint) = &fingerprint {
+            fingerprint.update(&config, status);
+        }
+        Ok(status)
+    }
+
+    /// Runs the command named by the first element of `args` with an already loaded [`Config`].
+    ///
+    /// This is used by commands which run other commands, e.g. `xtask ci`. Arguments following `--` are passed through
+    /// to the command as tool arguments. The command's prerequisites are not run; that is left to the
+    /// [`Executor`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - Argument processing fails (e.g. invalid arguments)
+    /// - The command is not registered
+    /// - Running the command fails
+    pub fn invoke(&self, config: &Config, options: &Options, args: Vec<OsString>) -> BoxResult<Option<ExitStatus>> {
+        let (mut args, tool_args) = split_tool_args(pico_args::Arguments::from_vec(args));
+        let name = args.subcommand()?.ok_or("expected an `xtask` command")?;
+        let task = self.lookup(&name)?;
+        let timeout = config.xtask.timeout(&name, first_free(&args).as_deref());
+        let mut context = Context::new(config, &mut args, tool_args);
+        context.registry = Some(self);
+        context.runner = &*self.runner;
+        context.options = options.clone();
+        context.options.timeout = timeout;
+        crate::report::with_task(&name, || task.run(context))
+    }
+
+    /// Computes the [`Fingerprint`] of running the xtask command line `args` (without the leading `xtask`).
+    ///
+    /// Returns `Ok(None)` if the cache is disabled, with `--dry-run` or `--no-cache`, or if the command declares no
+    /// [`Inputs`](crate::cache::Inputs).
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an input file cannot be read.
+    pub fn fingerprint(&self, config: &Config, options: &Options, args: &[OsString]) -> BoxResult<Option<Fingerprint>> {
+        if !config.xtask.cache.enabled || options.dry_run || options.no_cache {
+            return Ok(None);
+        }
+        let command = args.split(|arg| arg == "--").next().unwrap_or_default();
+        let Some((name, command_args)) = command.split_first() else {
+            return Ok(None);
+        };
+        let inputs = name
+            .to_str()
+            .and_then(|name| self.get(name))
+            .and_then(|task| task.inputs(command_args));
+        inputs
+            .map(|inputs| Fingerprint::compute(config, inputs, args))
+            .transpose()
+    }
+
+    fn lookup(&self, name: &str) -> BoxResult<&dyn Task> {
+        let Some(task) = self.get(name) else {
+            let suggestion = crate::suggest::did_you_mean(name, self.tasks().map(|task| task.spec().name));
+            if suggestion.is_empty() && Verbosity::current() > Verbosity::Quiet {
+                println!("{}\n", self.help());
+            }
+            let message = format!("unrecognized `xtask` command `{name}`{suggestion}");
+            return Err(XtaskError::bad_arguments(message).into());
+        };
+        Ok(task)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register(command::Build)
+            .register(command::Check)
+            .register(command::Ci)
+            .register(command::Clang)
+            .register(command::Clippy)
+            .register(command::CMake)
+            .register(command::Completions)
+            .register(command::Doc)
+            .register(command::Fmt)
+            .register(command::Miri)
+            .register(command::Tarpaulin)
+            .register(command::Test)
+            .register(command::Udeps)
+            .register(command::Valgrind)
+            .register(command::Watch);
+        registry
+    }
+}
+
+fn split_tool_args(args: pico_args::Arguments) -> (pico_args::Arguments, Vec<OsString>) {
+    let mut args = args.finish();
+    let tool_args = if let Some(index) = args.iter().position(|arg| arg == "--") {
+        let tool_args = args.split_off(index + 1);
+        args.pop();
+        tool_args
+    } else {
+        Vec::new()
+    };
+    (pico_args::Arguments::from_vec(args), tool_args)
+}
+
+/// Returns the first argument in `args` which is not a flag, e.g. the subcommand of commands which have them.
+fn first_free(args: &pico_args::Arguments) -> Option<String> {
+    let args = args.clone().finish();
+    let arg = args.into_iter().find(|arg| !arg.to_string_lossy().starts_with('-'))?;
+    Some(arg.to_string_lossy().into_owned())
+}
@@ -0,0 +1,130 @@
+use crate::{BoxError, BoxResult};
+use camino::Utf8Path;
+use serde::Serialize;
+use std::{
+    cell::{Cell, RefCell},
+    process::{Command, ExitStatus},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// A process spawned by an xtask command, as written to the `--report-json` file.
+///
+/// A command which fails before it spawns a process, e.g. because its tool is not found, is recorded as well, with the
+/// `error` but without an exit code.
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Serialize)]
+pub struct Record {
+    /// The name of the xtask command which spawned the process, e.g. `clippy`.
+    pub name: String,
+    pub argv: Vec<String>,
+    pub cwd: String,
+    /// When the process was started, in milliseconds since the Unix epoch.
+    pub start_time_ms: u64,
+    pub duration_ms: u64,
+    /// The exit code of the process, or `null` if it was terminated by a signal or did not run.
+    pub exit_code: Option<i32>,
+    /// The signal which terminated the process, if any.
+    pub signal: Option<i32>,
+    /// Why the process did not run, if it did not.
+    pub error: Option<String>,
+}
+
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static TASK: RefCell<Option<String>> = RefCell::new(None);
+    /// The number of records made on this thread.
+    static RECORDED: Cell<usize> = Cell::new(0);
+}
+
+/// Runs `f` with every process spawned on this thread attributed to the xtask command named `name`.
+///
+/// If `f` fails without a record having been made, e.g. because a configuration file is missing, the error is recorded
+/// in place of a process.
+///
+/// # Errors
+///
+/// Will return `Err` if `f` does.
+pub fn with_task<T>(name: &str, f: impl FnOnce() -> BoxResult<T>) -> BoxResult<T> {
+    let previous = TASK.with(|cell| cell.replace(Some(name.to_owned())));
+    let recorded = RECORDED.with(Cell::get);
+    let start = SystemTime::now();
+    let result = f();
+    if let Err(err) = &result {
+        if RECORDED.with(Cell::get) == recorded {
+            let cwd = std::env::current_dir()
+                .map(|cwd| cwd.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            push(Record {
+                name: name.to_owned(),
+                argv: Vec::new(),
+                cwd,
+                start_time_ms: millis_since_epoch(start),
+                duration_ms: millis(start.elapsed().unwrap_or_default()),
+                exit_code: None,
+                signal: None,
+                error: Some(err.to_string()),
+            });
+        }
+    }
+    TASK.with(|cell| cell.replace(previous));
+    result
+}
+
+/// Returns the name of the xtask command which processes spawned on this thread are attributed to.
+pub(crate) fn task() -> Option<String> {
+    TASK.with(|cell| cell.borrow().clone())
+}
+
+/// Records that `command` ran from `start` for `elapsed` and exited with `status`, or failed to run with `error`.
+pub(crate) fn record(command: &Command, start: SystemTime, elapsed: Duration, status: Result<ExitStatus, &BoxError>) {
+    let name = task().unwrap_or_default();
+    let argv = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let cwd = command
+        .get_current_dir()
+        .map(std::path::Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+        .map(|cwd| cwd.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    push(Record {
+        name,
+        argv,
+        cwd,
+        start_time_ms: millis_since_epoch(start),
+        duration_ms: millis(elapsed),
+        exit_code: status.ok().and_then(|status| status.code()),
+        signal: status.ok().and_then(crate::process::signal),
+        error: status.err().map(ToString::to_string),
+    });
+}
+
+fn push(record: Record) {
+    RECORDED.with(|recorded| recorded.set(recorded.get() + 1));
+    if let Ok(mut records) = RECORDS.lock() {
+        records.push(record);
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn millis_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, millis)
+}
+
+/// Writes every record collected so far to `path` as a JSON array.
+///
+/// # Errors
+///
+/// Will return `Err` if the report cannot be serialized or written to `path`.
+pub fn write(path: &Utf8Path) -> BoxResult<()> {
+    let records = RECORDS.lock().map_err(|_| "the `--report-json` records are poisoned")?;
+    let json = serde_json::to_string_pretty(&*records)?;
+    std::fs::write(path, json + "\n").map_err(|err| format!("failed to write `--report-json` to `{path}`: {err}"))?;
+    Ok(())
+}
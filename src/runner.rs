@@ -0,0 +1,88 @@
+use crate::{options::Options, BoxResult};
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::{Command, ExitStatus},
+    sync::Mutex,
+};
+
+/// Runs the processes spawned by commands.
+///
+/// Commands never spawn processes themselves but hand them to the [`Runner`] of their
+/// [`Context`](crate::command::Context), so that the processes can be inspected instead of run, e.g. in tests.
+pub trait Runner: Send + Sync {
+    /// Runs `cmd` to completion and returns its exit status, or `None` if no process was spawned.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the process cannot be run.
+    fn status(&self, options: &Options, cmd: &mut Command) -> BoxResult<Option<ExitStatus>>;
+}
+
+/// The [`Runner`] which spawns processes, as described for [`process::status`](crate::process::status).
+#[allow(clippy::module_name_repetitions)]
+pub struct ProcessRunner;
+
+impl Runner for ProcessRunner {
+    fn status(&self, options: &Options, cmd: &mut Command) -> BoxResult<Option<ExitStatus>> {
+        crate::process::status(options, cmd)
+    }
+}
+
+/// A process which a command asked the [`RecordingRunner`] to run.
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Recorded {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    pub current_dir: Option<PathBuf>,
+}
+
+impl Recorded {
+    /// Returns the program and arguments as strings, for convenient comparison.
+    #[must_use]
+    pub fn argv(&self) -> Vec<String> {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+}
+
+/// A [`Runner`] which records processes instead of running them, as with `--dry-run`.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Default)]
+pub struct RecordingRunner {
+    recorded: Mutex<Vec<Recorded>>,
+}
+
+impl RecordingRunner {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the processes recorded so far, in the order they were run, and forgets them.
+    #[must_use]
+    pub fn take(&self) -> Vec<Recorded> {
+        self.recorded
+            .lock()
+            .map(|mut recorded| std::mem::take(&mut *recorded))
+            .unwrap_or_default()
+    }
+}
+
+impl Runner for RecordingRunner {
+    fn status(&self, _options: &Options, cmd: &mut Command) -> BoxResult<Option<ExitStatus>> {
+        let recorded = Recorded {
+            program: cmd.get_program().to_os_string(),
+            args: cmd.get_args().map(ToOwned::to_owned).collect(),
+            current_dir: cmd.get_current_dir().map(ToOwned::to_owned),
+        };
+        self.recorded
+            .lock()
+            .map_err(|_| "the recorded processes are poisoned")?
+            .push(recorded);
+        Ok(None)
+    }
+}
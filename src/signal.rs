@@ -0,0 +1,354 @@
+use std::process::{Child, Command, ExitStatus};
+
+/// How long the process group of an interrupted child is given to exit before it is killed.
+#[cfg(unix)]
+const TEARDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Prepares `cmd` to be spawned in its own process group, so that it can be torn down together with any processes it
+/// spawns in turn.
+///
+/// This also installs the handler which forwards `SIGINT` and `SIGTERM` received by xtask to every such group. A
+/// signal received while no child is running exits xtask with code 128 + the number of the signal, and a second signal
+/// kills the children outright.
+///
+/// When xtask runs in the foreground of a terminal, the child is made the terminal's foreground process group while it
+/// runs, so that it can read from the terminal and receives a Ctrl-C or Ctrl-Z directly. Only one child can hold the
+/// terminal, so children run concurrently with it read their stdin from `/dev/null` instead, since reading from the
+/// terminal would stop them. Returns whether the child is to hold the terminal, to be passed on to [`Group::new`].
+#[must_use]
+pub fn isolate(cmd: &mut Command) -> bool {
+    #[cfg(unix)]
+    {
+        std::os::unix::process::CommandExt::process_group(cmd, 0);
+        unix::install();
+        let terminal = unix::claim_terminal();
+        if !terminal && std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+            cmd.stdin(std::process::Stdio::null());
+        }
+        terminal
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = cmd;
+        false
+    }
+}
+
+/// Gives up the terminal which [`isolate`] claimed for a child that then failed to spawn.
+pub fn release_terminal() {
+    #[cfg(unix)]
+    unix::release_terminal();
+}
+
+/// Returns the number of the signal which interrupted xtask, if any.
+#[must_use]
+pub fn received() -> Option<i32> {
+    #[cfg(unix)]
+    return unix::received();
+    #[cfg(not(unix))]
+    None
+}
+
+/// The process group of a child spawned after [`isolate`], registered to receive forwarded signals while it exists.
+pub struct Group {
+    #[cfg(unix)]
+    pgid: i32,
+    /// Whether the group holds the terminal, until it is dropped or xtask is resumed in the background.
+    #[cfg(unix)]
+    terminal: std::sync::atomic::AtomicBool,
+}
+
+impl Group {
+    /// Registers the group of `child`, handing it the terminal if `terminal`, as returned by [`isolate`], is set.
+    #[must_use]
+    pub fn new(child: &Child, terminal: bool) -> Self {
+        #[cfg(unix)]
+        {
+            let pgid = i32::try_from(child.id()).unwrap_or_default();
+            unix::register(pgid);
+            if terminal {
+                unix::give_terminal(pgid);
+            }
+            Self {
+                pgid,
+                terminal: terminal.into(),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (child, terminal);
+            Self {}
+        }
+    }
+
+    /// Called once the child has exited. If xtask was interrupted, waits for the rest of the group to exit, killing it
+    /// if it does not do so in time, and returns the number of the signal.
+    #[must_use]
+    pub fn finish(self) -> Option<i32> {
+        let signal = received()?;
+        #[cfg(unix)]
+        unix::teardown(self.pgid);
+        Some(signal)
+    }
+}
+
+impl Group {
+    /// Waits for `child` to exit and returns its exit status.
+    ///
+    /// If the group holds the terminal and the child is stopped, e.g. by Ctrl-Z, xtask takes back the terminal and
+    /// stops itself in turn, so that the shell regains the terminal. Once xtask is continued, the group is handed the
+    /// terminal again if xtask is in the foreground, and is continued.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if waiting for the child fails.
+    pub fn wait(&self, child: &mut Child) -> std::io::Result<ExitStatus> {
+        #[cfg(unix)]
+        if self.holds_terminal() {
+            if let Some(status) = unix::wait(self.pgid, &self.terminal, true)? {
+                return Ok(status);
+            }
+        }
+        child.wait()
+    }
+
+    /// Returns the exit status of `child` if it has exited, without blocking, handling a stopped child as [`Group::wait`]
+    /// does.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if waiting for the child fails.
+    pub fn try_wait(&self, child: &mut Child) -> std::io::Result<Option<ExitStatus>> {
+        #[cfg(unix)]
+        if self.holds_terminal() {
+            return unix::wait(self.pgid, &self.terminal, false);
+        }
+        child.try_wait()
+    }
+
+    #[cfg(unix)]
+    fn holds_terminal(&self) -> bool {
+        self.terminal.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Kills `child` together with every other process in its group.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the child cannot be killed.
+    pub fn kill(&self, child: &mut Child) -> std::io::Result<()> {
+        #[cfg(unix)]
+        unix::kill(self.pgid);
+        child.kill()
+    }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            if self.holds_terminal() {
+                unix::take_terminal();
+            }
+            unix::unregister(self.pgid);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use nix::{
+        errno::Errno,
+        sys::{
+            signal::{killpg, pthread_sigmask, SigSet, SigmaskHow, Signal},
+            wait::{waitpid, WaitPidFlag, WaitStatus},
+        },
+        unistd::{getpgrp, tcgetpgrp, tcsetpgrp, Pid},
+    };
+    use signal_hook::{
+        consts::{SIGINT, SIGTERM},
+        iterator::Signals,
+    };
+    use std::{
+        io::IsTerminal,
+        os::{fd::AsRawFd, unix::process::ExitStatusExt},
+        process::ExitStatus,
+        sync::{
+            atomic::{AtomicBool, AtomicI32, Ordering},
+            Mutex,
+            Once,
+        },
+        time::{Duration, Instant},
+    };
+
+    /// The process groups of the children currently running.
+    static GROUPS: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+    /// The last signal received, or 0.
+    static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+    static INSTALL: Once = Once::new();
+
+    /// Whether a child holds the terminal.
+    static TERMINAL: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn install() {
+        INSTALL.call_once(|| {
+            let Ok(mut signals) = Signals::new([SIGINT, SIGTERM]) else {
+                return;
+            };
+            std::thread::spawn(move || {
+                for signal in signals.forever() {
+                    // A repeated signal means the children are not exiting quickly enough, so they are killed.
+                    let repeated = RECEIVED.swap(signal, Ordering::SeqCst) != 0;
+                    let signal = if repeated { Signal::SIGKILL as i32 } else { signal };
+                    let Ok(groups) = GROUPS.lock() else {
+                        continue;
+                    };
+                    // Without children to wait for, xtask itself is what is being interrupted.
+                    if groups.is_empty() {
+                        std::process::exit(128 + RECEIVED.load(Ordering::SeqCst));
+                    }
+                    for &pgid in groups.iter() {
+                        forward(pgid, signal);
+                    }
+                }
+            });
+        });
+    }
+
+    pub(super) fn received() -> Option<i32> {
+        let signal = RECEIVED.load(Ordering::SeqCst);
+        (signal != 0).then_some(signal)
+    }
+
+    pub(super) fn register(pgid: i32) {
+        if let Ok(mut groups) = GROUPS.lock() {
+            groups.push(pgid);
+        }
+        // A signal received while the child was being spawned has not been forwarded to it.
+        if let Some(signal) = received() {
+            forward(pgid, signal);
+        }
+    }
+
+    pub(super) fn unregister(pgid: i32) {
+        if let Ok(mut groups) = GROUPS.lock() {
+            groups.retain(|&group| group != pgid);
+        }
+    }
+
+    pub(super) fn teardown(pgid: i32) {
+        let deadline = Instant::now() + super::TEARDOWN_TIMEOUT;
+        while killpg(Pid::from_raw(pgid), None).is_ok() {
+            if Instant::now() >= deadline {
+                killpg(Pid::from_raw(pgid), Signal::SIGKILL).ok();
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    pub(super) fn kill(pgid: i32) {
+        killpg(Pid::from_raw(pgid), Signal::SIGKILL).ok();
+    }
+
+    /// Returns whether the next child is to hold the terminal: xtask must be in the foreground of the terminal on its
+    /// stdin, and no other child may hold it.
+    pub(super) fn claim_terminal() -> bool {
+        let stdin = std::io::stdin();
+        stdin.is_terminal()
+            && tcgetpgrp(stdin.as_raw_fd()).map_or(false, |foreground| foreground == getpgrp())
+            && TERMINAL
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+    }
+
+    pub(super) fn give_terminal(pgid: i32) {
+        without_sigttou(|| tcsetpgrp(std::io::stdin().as_raw_fd(), Pid::from_raw(pgid)).ok());
+        // The child may have read from the terminal, and been stopped, before it was handed the terminal.
+        killpg(Pid::from_raw(pgid), Signal::SIGCONT).ok();
+    }
+
+    pub(super) fn take_terminal() {
+        without_sigttou(|| tcsetpgrp(std::io::stdin().as_raw_fd(), getpgrp()).ok());
+        release_terminal();
+    }
+
+    pub(super) fn release_terminal() {
+        TERMINAL.store(false, Ordering::SeqCst);
+    }
+
+    /// Waits for the child leading the group `pgid`, which holds the terminal while `terminal` is set, blocking if
+    /// `block` is set. Returns `None` if the child has not exited without blocking.
+    ///
+    /// The child is reaped here rather than by [`std::process::Child`], since only `waitpid` reports it being stopped.
+    pub(super) fn wait(pgid: i32, terminal: &AtomicBool, block: bool) -> std::io::Result<Option<ExitStatus>> {
+        let flags = if block {
+            WaitPidFlag::WUNTRACED
+        } else {
+            WaitPidFlag::WUNTRACED | WaitPidFlag::WNOHANG
+        };
+        loop {
+            match waitpid(Pid::from_raw(pgid), Some(flags)) {
+                Ok(WaitStatus::Exited(_, code)) => return Ok(Some(ExitStatus::from_raw(code << 8))),
+                Ok(WaitStatus::Signaled(_, signal, core_dumped)) => {
+                    let core_dumped = if core_dumped { 0x80 } else { 0 };
+                    return Ok(Some(ExitStatus::from_raw(signal as i32 | core_dumped)));
+                },
+                Ok(WaitStatus::Stopped(..)) => suspend(pgid, terminal),
+                Ok(WaitStatus::StillAlive) => return Ok(None),
+                Ok(_) | Err(Errno::EINTR) => {},
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Stops xtask, whose child leading the group `pgid` was stopped, and continues the group once xtask is continued.
+    ///
+    /// The group is handed the terminal again if xtask is continued in the foreground. Continued in the background,
+    /// e.g. with `bg`, the terminal is left to the shell.
+    fn suspend(pgid: i32, terminal: &AtomicBool) {
+        if terminal.load(Ordering::SeqCst) {
+            without_sigttou(|| tcsetpgrp(std::io::stdin().as_raw_fd(), getpgrp()).ok());
+        }
+        // The whole group of xtask is stopped, as Ctrl-Z would have done, so that e.g. the `cargo` of `cargo xtask`
+        // stops as well and the shell notices.
+        killpg(getpgrp(), Signal::SIGTSTP).ok();
+        let foreground = tcgetpgrp(std::io::stdin().as_raw_fd()).map_or(false, |foreground| foreground == getpgrp());
+        let held = terminal.load(Ordering::SeqCst);
+        if foreground
+            && (held
+                || TERMINAL
+                    .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok())
+        {
+            terminal.store(true, Ordering::SeqCst);
+            give_terminal(pgid);
+        } else {
+            if held {
+                terminal.store(false, Ordering::SeqCst);
+                release_terminal();
+            }
+            killpg(Pid::from_raw(pgid), Signal::SIGCONT).ok();
+        }
+    }
+
+    /// Runs `f` with `SIGTTOU` blocked, which would otherwise stop xtask for changing the terminal's foreground process
+    /// group while not in it.
+    fn without_sigttou<T>(f: impl FnOnce() -> T) -> T {
+        let mut sigttou = SigSet::empty();
+        sigttou.add(Signal::SIGTTOU);
+        let mut previous = SigSet::empty();
+        pthread_sigmask(SigmaskHow::SIG_BLOCK, Some(&sigttou), Some(&mut previous)).ok();
+        let result = f();
+        pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&previous), None).ok();
+        result
+    }
+
+    fn forward(pgid: i32, signal: i32) {
+        if let Ok(signal) = Signal::try_from(signal) {
+            killpg(Pid::from_raw(pgid), signal).ok();
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{config::Config, BoxResult};
+use camino::Utf8PathBuf;
+use std::{
+    fs::Metadata,
+    path::{Path, PathBuf},
+};
+
+/// File extensions of the C++ sources and `CMake` scripts of a project.
+pub const CXX_EXTENSIONS: [&str; 9] = ["c", "cc", "cmake", "cpp", "cxx", "h", "hh", "hpp", "hxx"];
+
+/// The source tree of a workspace: the workspace root plus any members outside of it.
+///
+/// The cargo target directory, the `target/` directory at the workspace root, the `CMake` build directories, and hidden
+/// directories (e.g. `.git/`) are never scanned.
+pub struct Sources {
+    workspace_root: PathBuf,
+    members: Vec<PathBuf>,
+    ignored: Vec<PathBuf>,
+}
+
+impl Sources {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        let metadata = &config.cargo_metadata;
+        let workspace_root = metadata.workspace_root.clone().into_std_path_buf();
+        let members = metadata
+            .workspace_packages()
+            .into_iter()
+            .filter_map(|package| package.manifest_path.parent())
+            .map(|path| path.to_path_buf().into_std_path_buf())
+            .collect();
+        let mut ignored = vec![
+            metadata.target_directory.clone().into_std_path_buf(),
+            workspace_root.join("target"),
+        ];
+        ignored.extend(
+            config
+                .cmake_build_dirs()
+                .into_iter()
+                .map(Utf8PathBuf::into_std_path_buf),
+        );
+        Self {
+            workspace_root,
+            members,
+            ignored,
+        }
+    }
+
+    #[must_use]
+    pub fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+
+    /// Returns the directories of the workspace members.
+    #[must_use]
+    pub fn members(&self) -> &[PathBuf] {
+        &self.members
+    }
+
+    /// Returns every file in the source tree for which `filter` holds, together with its metadata, sorted by path.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a directory or file in the source tree cannot be read.
+    pub fn files(&self, filter: impl Fn(&Path) -> bool) -> BoxResult<Vec<(PathBuf, Metadata)>> {
+        let mut files = Vec::new();
+        self.scan(&self.workspace_root, &filter, &mut files)?;
+        for member in &self.members {
+            if !member.starts_with(&self.workspace_root) {
+                self.scan(member, &filter, &mut files)?;
+            }
+        }
+        files.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        Ok(files)
+    }
+
+    fn scan(&self, dir: &Path, filter: &impl Fn(&Path) -> bool, files: &mut Vec<(PathBuf, Metadata)>) -> BoxResult<()> {
+        // Files may be removed while the tree is scanned, e.g. the temporary files of an editor, and are then skipped.
+        let Some(entries) = found(std::fs::read_dir(dir))? else {
+            return Ok(());
+        };
+        for entry in entries {
+            let Some(entry) = found(entry)? else {
+                continue;
+            };
+            let Some(file_type) = found(entry.file_type())? else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if !hidden && !self.ignored.contains(&path) {
+                    self.scan(&path, filter, files)?;
+                }
+            } else if filter(&path) {
+                if let Some(metadata) = found(entry.metadata())? {
+                    files.push((path, metadata));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns `None` instead of the error if `result` failed because the file was not found.
+fn found<T>(result: std::io::Result<T>) -> std::io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns whether `path` is a C++ source or `CMake` script.
+#[must_use]
+pub fn is_cxx(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| name == "CMakeLists.txt")
+        || path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map_or(false, |extension| CXX_EXTENSIONS.contains(&extension))
+}
@@ -0,0 +1,287 @@
+use crate::{
+    error::XtaskError,
+    options::{Options, Verbosity},
+    BoxError,
+    BoxResult,
+};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Width of the left-hand column in generated help text.
+const COLUMN: usize = 20;
+
+/// A flag accepted by a command, e.g. `-p, --package <SPEC>`.
+pub struct Flag {
+    pub short: Option<&'static str>,
+    pub long: &'static str,
+    pub value: Option<&'static str>,
+    pub about: &'static str,
+}
+
+impl Flag {
+    /// Creates a flag which takes no value.
+    #[must_use]
+    pub const fn new(long: &'static str, about: &'static str) -> Self {
+        Self {
+            short: None,
+            long,
+            value: None,
+            about,
+        }
+    }
+
+    /// Sets the short form of the flag, e.g. `-p`.
+    #[must_use]
+    pub const fn short(mut self, short: &'static str) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    /// Makes the flag take a value, displayed as `<name>` in the help text.
+    #[must_use]
+    pub const fn value(mut self, name: &'static str) -> Self {
+        self.value = Some(name);
+        self
+    }
+
+    /// Returns the short form of the flag, if any, followed by its long form.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &'static str> {
+        self.short.into_iter().chain([self.long])
+    }
+
+    fn keys(&self) -> pico_args::Keys {
+        match self.short {
+            Some(short) => [short, self.long].into(),
+            None => self.long.into(),
+        }
+    }
+
+    pub(crate) fn label(&self) -> String {
+        let mut label = String::new();
+        if let Some(short) = self.short {
+            write!(label, "{short}, ").ok();
+        }
+        label.push_str(self.long);
+        if let Some(value) = self.value {
+            write!(label, " <{value}>").ok();
+        }
+        label
+    }
+}
+
+/// A subcommand accepted by a command, e.g. `tidy` for `xtask clang`.
+pub struct Subcommand {
+    pub name: &'static str,
+    pub about: &'static str,
+}
+
+impl Subcommand {
+    #[must_use]
+    pub const fn new(name: &'static str, about: &'static str) -> Self {
+        Self { name, about }
+    }
+}
+
+/// The command line interface of a command.
+///
+/// Both argument parsing (via [`Spec::parse`]) and the `--help` output (via [`Spec::help`]) are derived from the
+/// spec, so the two cannot drift apart.
+pub struct Spec {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub flags: &'static [Flag],
+    pub subcommands: &'static [Subcommand],
+    pub tool: Option<&'static str>,
+}
+
+impl Spec {
+    #[must_use]
+    pub const fn new(name: &'static str, about: &'static str) -> Self {
+        Self {
+            name,
+            about,
+            flags: &[],
+            subcommands: &[],
+            tool: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn flags(mut self, flags: &'static [Flag]) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    #[must_use]
+    pub const fn subcommands(mut self, subcommands: &'static [Subcommand]) -> Self {
+        self.subcommands = subcommands;
+        self
+    }
+
+    /// Sets the description of the tool which receives the arguments following `--`.
+    #[must_use]
+    pub const fn tool(mut self, tool: &'static str) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    #[must_use]
+    pub fn subcommand(&self, name: &str) -> Option<&Subcommand> {
+        self.subcommands.iter().find(|subcommand| subcommand.name == name)
+    }
+
+    #[must_use]
+    pub fn help(&self) -> String {
+        let name = self.name;
+        let mut help = String::new();
+        writeln!(help, "xtask-{name}\n").ok();
+        writeln!(help, "USAGE:").ok();
+        write!(help, "xtask {name}").ok();
+        if !self.subcommands.is_empty() {
+            help.push_str(" [SUBCOMMAND]");
+        }
+        if !self.flags.is_empty() {
+            help.push_str(" [FLAGS]");
+        }
+        help.push_str("\n\nFLAGS:\n");
+        write_row(&mut help, "", "-h, --help", "Prints help information");
+        for flag in self.flags {
+            write_row(&mut help, "", &flag.label(), flag.about);
+        }
+        if let Some(tool) = self.tool {
+            write_row(
+                &mut help,
+                "",
+                "-- '...'",
+                &format!("Extra arguments to pass to the {tool}"),
+            );
+        }
+        if !self.subcommands.is_empty() {
+            help.push_str("\nSUBCOMMANDS:\n");
+            for subcommand in self.subcommands {
+                write_row(&mut help, "    ", subcommand.name, subcommand.about);
+            }
+        }
+        help.truncate(help.trim_end().len());
+        help
+    }
+
+    /// Parses the flags and subcommand in `args` according to the spec.
+    ///
+    /// Returns `Ok(None)` if help was requested, in which case the help text has already been printed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` under the following circumstances:
+    /// - A flag value is not valid UTF-8
+    /// - The subcommand is not one of the spec's subcommands
+    /// - Unrecognized arguments remain after parsing
+    pub fn parse(&self, args: &mut pico_args::Arguments) -> BoxResult<Option<Matches>> {
+        let help = self.help();
+
+        if crate::handler::help(args, &help)? {
+            return Ok(None);
+        }
+
+        let mut matches = Matches::parse(self.flags, args)?;
+
+        if !self.subcommands.is_empty() {
+            if let Some(subcommand) = args.opt_free_from_str::<String>()? {
+                if self.subcommand(&subcommand).is_none() {
+                    return Err(self.unrecognized_subcommand(&subcommand));
+                }
+                matches.subcommand = Some(subcommand);
+            }
+        }
+
+        // The global options have been parsed already, but are offered as suggestions for misspellings of them.
+        let mut known = vec!["-h", "--help"];
+        known.extend(self.flags.iter().chain(Options::FLAGS).flat_map(Flag::names));
+        crate::handler::unrecognized(args, &known)?;
+
+        Ok(Some(matches))
+    }
+
+    /// Builds the error for an unrecognized `subcommand`, suggesting the closest known subcommand.
+    ///
+    /// The help text is printed if there is no plausible suggestion.
+    #[must_use]
+    pub fn unrecognized_subcommand(&self, subcommand: &str) -> BoxError {
+        let name = self.name;
+        let suggestion = crate::suggest::did_you_mean(subcommand, self.subcommands.iter().map(|s| s.name));
+        if suggestion.is_empty() && Verbosity::current() > Verbosity::Quiet {
+            println!("{}\n", self.help());
+        }
+        XtaskError::bad_arguments(format!(
+            "unrecognized `xtask {name}` subcommand `{subcommand}`{suggestion}"
+        ))
+        .into()
+    }
+}
+
+/// The flags and subcommand parsed from the command line by [`Spec::parse`].
+#[derive(Default)]
+pub struct Matches {
+    pub subcommand: Option<String>,
+    flags: BTreeMap<&'static str, Vec<String>>,
+}
+
+impl Matches {
+    /// Extracts occurrences of `flags` from `args`, leaving any other arguments in place.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a flag value is not valid UTF-8.
+    pub fn parse(flags: &'static [Flag], args: &mut pico_args::Arguments) -> BoxResult<Self> {
+        let mut matches = Self::default();
+        for flag in flags {
+            let values = if flag.value.is_some() {
+                args.values_from_str::<_, String>(flag.keys())?
+            } else {
+                std::iter::from_fn(|| args.contains(flag.keys()).then(String::new)).collect()
+            };
+            if !values.is_empty() {
+                matches.flags.insert(flag.long, values);
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Returns the number of times the flag named `long` occurred.
+    #[must_use]
+    pub fn count(&self, long: &str) -> usize {
+        self.flags.get(long).map_or(0, Vec::len)
+    }
+
+    #[must_use]
+    pub fn contains(&self, long: &str) -> bool {
+        self.count(long) > 0
+    }
+
+    /// Returns the last value given for the flag named `long`.
+    #[must_use]
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values(long).last().map(String::as_str)
+    }
+
+    /// Returns every value given for the flag named `long`, in command line order.
+    #[must_use]
+    pub fn values(&self, long: &str) -> &[String] {
+        self.flags.get(long).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Writes a `label    about` row to `help`, aligning multi-line descriptions under the first line.
+pub(crate) fn write_row(help: &mut String, indent: &str, label: &str, about: &str) {
+    let column = COLUMN.saturating_sub(indent.len());
+    let mut lines = about.lines();
+    let first = lines.next().unwrap_or_default();
+    if label.len() < column {
+        writeln!(help, "{indent}{label:<column$}{first}").ok();
+    } else {
+        writeln!(help, "{indent}{label}").ok();
+        writeln!(help, "{indent}{:<column$}{first}", "").ok();
+    }
+    for line in lines {
+        writeln!(help, "{indent}{:<column$}{line}", "").ok();
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests;
+
+/// Returns the candidate closest to `input` by edit distance, if one is close enough to be a plausible typo.
+///
+/// Case is ignored. Inputs of up to two characters, e.g. short flags, only match a candidate which differs in case,
+/// since any other candidate of that length would be a single edit away.
+pub fn closest<'a>(input: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let length = input.chars().count();
+    let threshold = if length > 2 { length / 3 } else { 0 };
+    let input = input.to_lowercase();
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(&input, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Formats a `; did you mean ...?` suffix for an error message about `input`, or an empty string if no candidate is
+/// close enough.
+pub fn did_you_mean<'a>(input: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    closest(input, candidates).map_or_else(String::new, |candidate| format!("; did you mean `{candidate}`?"))
+}
+
+/// Computes the Levenshtein distance between `lhs` and `rhs`.
+fn distance(lhs: &str, rhs: &str) -> usize {
+    let rhs = rhs.chars().collect::<Vec<_>>();
+    let mut row = (0 ..= rhs.len()).collect::<Vec<_>>();
+    for (i, lhs) in lhs.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &rhs) in rhs.iter().enumerate() {
+            let substitution = diagonal + usize::from(lhs != rhs);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[rhs.len()]
+}
@@ -0,0 +1,36 @@
+use super::{closest, did_you_mean, distance};
+
+#[test]
+fn distance_counts_edits() {
+    assert_eq!(distance("", ""), 0);
+    assert_eq!(distance("", "fmt"), 3);
+    assert_eq!(distance("clippy", "clippy"), 0);
+    assert_eq!(distance("clipy", "clippy"), 1);
+    assert_eq!(distance("tidy", "tidi"), 1);
+    assert_eq!(distance("kitten", "sitting"), 3);
+}
+
+#[test]
+fn closest_suggests_plausible_typos() {
+    let commands = ["build", "check", "clippy", "tarpaulin"];
+    assert_eq!(closest("buld", commands), Some("build"));
+    assert_eq!(closest("clipy", commands), Some("clippy"));
+    assert_eq!(closest("tarpolin", commands), Some("tarpaulin"));
+    assert_eq!(closest("Check", commands), Some("check"));
+    assert_eq!(closest("deploy", commands), None);
+}
+
+#[test]
+fn closest_only_matches_short_inputs_up_to_case() {
+    let flags = ["-h", "--help", "-p"];
+    assert_eq!(closest("-x", flags), None);
+    assert_eq!(closest("-P", flags), Some("-p"));
+    assert_eq!(closest("x", ["h"]), None);
+    assert_eq!(closest("--hlep", flags), Some("--help"));
+}
+
+#[test]
+fn did_you_mean_formats_suggestion() {
+    assert_eq!(did_you_mean("clipy", ["clippy"]), "; did you mean `clippy`?");
+    assert_eq!(did_you_mean("-x", ["-h"]), "");
+}
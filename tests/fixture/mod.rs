@@ -0,0 +1,198 @@
+use std::{
+    ffi::OsString,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// The toolchain channel written to the fixture's `rust-toolchain.toml`.
+pub const CHANNEL: &str = "nightly-2023-08-08";
+
+/// A temporary cxx-auto workspace, with stub tools which record their invocations instead of doing any work.
+///
+/// The workspace holds a single package named `fixture`. The stubs live in a `bin` directory next to it which is put
+/// first on the `PATH` of xtask, and `build/cxx-auto-context.json` points at the stubs for the clang tools.
+/// `cargo metadata` is unaffected by the `cargo` stub since xtask runs the `cargo` named by the `CARGO` environment
+/// variable for it, which `cargo test` sets.
+pub struct Fixture {
+    dir: tempfile::TempDir,
+}
+
+/// An invocation of a stub tool.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Call {
+    /// The directory the stub was run from.
+    pub cwd: PathBuf,
+    /// The name of the stub followed by its arguments, separated by spaces.
+    pub argv: String,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().expect("create fixture directory");
+        let fixture = Self { dir };
+        fixture.write(
+            "Cargo.toml",
+            "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        );
+        fixture.write("src/lib.rs", "");
+        fixture.write(
+            "rust-toolchain.toml",
+            &format!("[toolchain]\nchannel = \"{CHANNEL}\"\n"),
+        );
+        fixture.write("build/cxx-auto-context.json", &fixture.cmake_context());
+        for tool in [
+            "cargo",
+            "cmake",
+            "clang-format",
+            "clang-tidy",
+            "run-clang-format",
+            "run-clang-tidy",
+        ] {
+            fixture.stub(tool, 0);
+        }
+        fixture
+    }
+
+    /// The root of the fixture workspace.
+    pub fn root(&self) -> PathBuf {
+        self.dir.path().join("workspace")
+    }
+
+    /// The directory holding the stub tools.
+    pub fn bin(&self) -> PathBuf {
+        self.dir.path().join("bin")
+    }
+
+    /// Writes `contents` to `path`, relative to the workspace root, creating its parent directories.
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.root().join(path);
+        std::fs::create_dir_all(path.parent().expect("path has a parent")).expect("create parent directory");
+        std::fs::write(&path, contents).expect("write fixture file");
+    }
+
+    /// Sets the modification time of `path`, relative to the workspace root, to long ago.
+    pub fn age(&self, path: &str) {
+        let status = Command::new("touch")
+            .args(["-t", "200001010000"])
+            .arg(self.root().join(path))
+            .status()
+            .expect("run touch");
+        assert!(status.success(), "failed to age `{path}`");
+    }
+
+    /// Removes `path`, relative to the workspace root.
+    pub fn remove(&self, path: &str) {
+        std::fs::remove_file(self.root().join(path)).expect("remove fixture file");
+    }
+
+    /// Installs a stub for `tool` which records its invocation and exits with `code`.
+    pub fn stub(&self, tool: &str, code: i32) {
+        self.stub_with(tool, "", code);
+    }
+
+    /// Installs a stub for `tool` which records its invocation, runs the shell commands `body`, and exits with `code`.
+    pub fn stub_with(&self, tool: &str, body: &str, code: i32) {
+        let log = self.log();
+        let script = format!(
+            "#!/bin/sh\nprintf '%s\\t%s\\n' \"$PWD\" \"{tool} $*\" >> '{}'\n{body}\nexit {code}\n",
+            log.display()
+        );
+        let path = self.bin().join(tool);
+        std::fs::create_dir_all(self.bin()).expect("create stub directory");
+        std::fs::write(&path, script).expect("write stub");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).expect("make stub executable");
+    }
+
+    /// Removes the stub for `tool`, as if the tool were not installed.
+    pub fn unstub(&self, tool: &str) {
+        std::fs::remove_file(self.bin().join(tool)).expect("remove stub");
+    }
+
+    /// Returns the invocations of the stubs so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        let Ok(log) = std::fs::read_to_string(self.log()) else {
+            return Vec::new();
+        };
+        log.lines()
+            .map(|line| {
+                let (cwd, argv) = line.split_once('\t').expect("well-formed stub log");
+                Call {
+                    cwd: PathBuf::from(cwd),
+                    argv: argv.trim_end().to_owned(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the argument lines of the stub invocations so far, in order.
+    pub fn argvs(&self) -> Vec<String> {
+        self.calls().into_iter().map(|call| call.argv).collect()
+    }
+
+    /// Runs xtask with `args` from the workspace root.
+    pub fn xtask(&self, args: &[&str]) -> Output {
+        self.xtask_in(&self.root(), args)
+    }
+
+    /// Runs xtask with `args` from `dir`.
+    pub fn xtask_in(&self, dir: &Path, args: &[&str]) -> Output {
+        self.command(env!("CARGO_BIN_EXE_cxx-auto-xtask"))
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("run xtask")
+    }
+
+    /// Returns a command running `program` from the workspace root, with the stubs first on `PATH`.
+    pub fn command(&self, program: impl AsRef<std::ffi::OsStr>) -> Command {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let path =
+            std::env::join_paths(std::iter::once(self.bin()).chain(std::env::split_paths(&path))).expect("join PATH");
+        let mut command = Command::new(program);
+        command.current_dir(self.root()).env("PATH", path).env("CARGO", cargo());
+        command
+    }
+
+    /// The contents of the fixture's `build/cxx-auto-context.json`, pointing at the stubs for the clang tools.
+    pub fn cmake_context(&self) -> String {
+        serde_json::json!({
+            "BIN_CLANG_FORMAT": self.bin().join("clang-format"),
+            "BIN_CLANG_TIDY": self.bin().join("clang-tidy"),
+            "BIN_RUN_CLANG_FORMAT": self.bin().join("run-clang-format"),
+            "BIN_RUN_CLANG_TIDY": self.bin().join("run-clang-tidy"),
+        })
+        .to_string()
+    }
+
+    /// Makes the `cmake` stub write `build/cxx-auto-context.json`, as configuring a cxx-auto project does.
+    pub fn stub_cmake_configure(&self) {
+        let path = self.root().join("build/cxx-auto-context.json");
+        let body = format!(
+            "mkdir -p build && printf '%s' '{}' > '{}'",
+            self.cmake_context(),
+            path.display()
+        );
+        self.stub_with("cmake", &body, 0);
+    }
+
+    fn log(&self) -> PathBuf {
+        self.dir.path().join("calls.log")
+    }
+}
+
+/// The `cargo` which runs the tests, which xtask uses for `cargo metadata`.
+fn cargo() -> OsString {
+    std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"))
+}
+
+/// Returns the exit code of `output`, printing its stderr to ease debugging failed assertions.
+pub fn code(output: &Output) -> Option<i32> {
+    eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+    output.status.code()
+}
+
+/// Returns the stderr of `output` as text.
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,632 @@
+#![cfg(unix)]
+
+mod fixture;
+
+use cxx_auto_xtask::config::Config;
+use fixture::{code, stderr, Fixture, CHANNEL};
+
+#[test]
+fn config_loads_fixture() {
+    let fixture = Fixture::new();
+    let root = camino::Utf8PathBuf::try_from(fixture.root()).expect("UTF-8 fixture path");
+    let config = Config::load_from(Some(&root), None).expect("load fixture configuration");
+    let rust_toolchain = config.rust_toolchain("test").expect("load rust toolchain");
+    assert_eq!(rust_toolchain.toolchain.channel, CHANNEL);
+    let cmake_context = config.cmake_context("test").expect("load CMake context");
+    assert_eq!(cmake_context.bin_run_clang_tidy, fixture.bin().join("run-clang-tidy"));
+    assert_eq!(config.cargo_metadata.workspace_packages()[0].name, "fixture");
+}
+
+#[test]
+fn clang_format_runs_configured_tools() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    let clang_format = fixture.bin().join("clang-format");
+    assert_eq!(fixture.argvs(), [format!(
+        "run-clang-format --clang-format-executable {}",
+        clang_format.display()
+    )]);
+}
+
+#[test]
+fn clang_tidy_runs_prerequisites_first() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask(&["clang", "tidy", "--", "-fix"]);
+    assert_eq!(code(&output), Some(0));
+    let clang_tidy = fixture.bin().join("clang-tidy");
+    assert_eq!(fixture.argvs(), [
+        String::from("cargo check --package fixture"),
+        String::from("cmake -G Ninja -S . -B build"),
+        format!("run-clang-tidy -clang-tidy-binary {} -fix", clang_tidy.display()),
+    ]);
+}
+
+#[test]
+fn tools_run_from_workspace_root() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask_in(&fixture.root().join("src"), &["cmake", "build"]);
+    assert_eq!(code(&output), Some(0));
+    let calls = fixture.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].argv, "cmake -G Ninja -S . -B build");
+    assert_eq!(calls[0].cwd, fixture.root().canonicalize().expect("canonical root"));
+}
+
+#[test]
+fn directory_flag_selects_workspace() {
+    let fixture = Fixture::new();
+    let root = fixture.root();
+    let output = fixture.xtask_in(std::env::temp_dir().as_path(), &[
+        "-C",
+        root.to_str().expect("UTF-8 fixture path"),
+        "fmt",
+    ]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs(), [format!("cargo +{CHANNEL} fmt --all")]);
+}
+
+#[test]
+fn workspace_configuration_is_applied() {
+    let fixture = Fixture::new();
+    fixture.write(
+        "Cargo.toml",
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n\n\
+         [workspace.metadata.xtask.cmake]\ngenerator = \"Unix Makefiles\"\n",
+    );
+    let output = fixture.xtask(&["cmake", "build"]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs(), ["cmake -G Unix Makefiles -S . -B build"]);
+}
+
+#[test]
+fn build_dir_selects_cmake_context() {
+    let fixture = Fixture::new();
+    fixture.write(
+        "Cargo.toml",
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n\n\
+         [workspace.metadata.xtask.cmake.build-dirs]\nasan = { args = [\"-DSANITIZE=address\"] }\n",
+    );
+    fixture.remove("build/cxx-auto-context.json");
+    fixture.write("build/asan/cxx-auto-context.json", &fixture.cmake_context());
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(3));
+    let output = fixture.xtask(&["--build-dir", "asan", "clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    let output = fixture.xtask(&["--build-dir", "asan", "cmake", "build"]);
+    assert_eq!(code(&output), Some(0));
+    let argvs = fixture.argvs();
+    assert!(argvs[0].starts_with("run-clang-format"));
+    assert_eq!(argvs[1], "cmake -G Ninja -S . -B build/asan -DSANITIZE=address");
+    let output = fixture.xtask(&["--build-dir", "asn", "cmake", "build"]);
+    assert_eq!(code(&output), Some(2));
+    assert!(stderr(&output).contains("did you mean `asan`?"));
+    let output = fixture.xtask(&["--build-dir", "./scratch", "cmake", "build"]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs()[2 ..], ["cmake -G Ninja -S . -B scratch"]);
+    let output = fixture.xtask(&["--build-dir", "debg", "cmake", "build"]);
+    assert_eq!(code(&output), Some(2));
+    assert!(stderr(&output).contains("use `./debg` for a new directory"));
+}
+
+#[test]
+fn cached_clang_format_reruns_when_tools_change() {
+    let fixture = Fixture::new();
+    fixture.write(
+        "Cargo.toml",
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n\n\
+         [workspace.metadata.xtask.cache]\nenabled = true\n",
+    );
+    for _ in 0 .. 2 {
+        let output = fixture.xtask(&["clang", "format"]);
+        assert_eq!(code(&output), Some(0));
+    }
+    assert_eq!(fixture.argvs().len(), 1);
+    let clang_format = fixture.bin().join("clang-format-16");
+    let context = fixture.cmake_context().replace(
+        &*fixture.bin().join("clang-format").to_string_lossy(),
+        &clang_format.to_string_lossy(),
+    );
+    fixture.write("build/cxx-auto-context.json", &context);
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs()[1 ..], [format!(
+        "run-clang-format --clang-format-executable {}",
+        clang_format.display()
+    )]);
+}
+
+#[test]
+fn failing_tool_exit_code_is_propagated() {
+    let fixture = Fixture::new();
+    fixture.stub("run-clang-format", 6);
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(6));
+    assert!(stderr(&output).contains("failed with exit code 6"));
+    // Exit codes reserved for xtask's own errors are not passed through.
+    fixture.stub("run-clang-format", 3);
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(1));
+    assert!(stderr(&output).contains("failed with exit code 3"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn timeout_kills_tool_process_group() {
+    let fixture = Fixture::new();
+    fixture.write(
+        "Cargo.toml",
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n\n\
+         [workspace.metadata.xtask.timeouts]\n\"cmake build\" = 1\n",
+    );
+    // The stub waits for a process of its own, which must be killed along with it.
+    let pid = fixture.root().join("sleep.pid");
+    fixture.stub_with("cmake", &format!("sleep 30 &\necho $! > '{}'\nwait", pid.display()), 0);
+    let start = std::time::Instant::now();
+    let output = fixture.xtask(&["cmake", "build"]);
+    assert_eq!(code(&output), Some(124));
+    assert!(start.elapsed() < std::time::Duration::from_secs(20));
+    assert!(stderr(&output).contains("timed out after 1."));
+    // The killed `sleep` may linger as a zombie until it is reaped.
+    let pid = std::fs::read_to_string(pid).expect("read pid");
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim())).unwrap_or_default();
+    let state = stat.rsplit(") ").next().and_then(|rest| rest.chars().next());
+    assert!(
+        matches!(state, None | Some('Z' | 'X')),
+        "the stub's `sleep` survived the timeout"
+    );
+}
+
+#[test]
+fn log_dir_lists_logs_of_failed_commands() {
+    let fixture = Fixture::new();
+    fixture.stub_with("run-clang-tidy", "echo 'warning: tidy'", 5);
+    let output = fixture.xtask(&["--log-dir", "logs", "clang", "tidy"]);
+    assert_eq!(code(&output), Some(5));
+    let logs = fixture.root().join("logs");
+    let mut names = std::fs::read_dir(&logs)
+        .expect("read log directory")
+        .map(|entry| entry.expect("log entry").file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(names, ["01-check.log", "02-cmake.log", "03-clang.log"]);
+    let log = std::fs::read_to_string(logs.join("03-clang.log")).expect("read log");
+    let run_clang_tidy = fixture.bin().join("run-clang-tidy");
+    assert_eq!(log.lines().collect::<Vec<_>>(), [
+        &*format!(
+            "$ cd {} && {} -clang-tidy-binary {}",
+            fixture.root().display(),
+            run_clang_tidy.display(),
+            fixture.bin().join("clang-tidy").display()
+        ),
+        "warning: tidy",
+    ]);
+    let errors = stderr(&output);
+    let (_, listed) = errors
+        .split_once("logs of the failed commands:\n")
+        .expect("failed logs listed");
+    assert_eq!(listed.trim(), "logs/03-clang.log");
+
+    fixture.unstub("run-clang-format");
+    let output = fixture.xtask(&["--log-dir", "logs", "clang", "format"]);
+    assert_eq!(code(&output), Some(127));
+    let log = std::fs::read_to_string(logs.join("01-clang.log")).expect("read log");
+    assert!(log.lines().nth(1).is_some_and(|line| line.contains("run-clang-format")));
+    assert!(stderr(&output).ends_with("logs of the failed commands:\n    logs/01-clang.log\n"));
+}
+
+#[test]
+fn report_json_records_processes_and_early_failures() {
+    let fixture = Fixture::new();
+    let report = fixture.root().join("report.json");
+    let read_report = || -> Vec<serde_json::Value> {
+        let report = std::fs::read_to_string(&report).expect("read report");
+        serde_json::from_str(&report).expect("parse report")
+    };
+    let output = fixture.xtask(&[
+        "--report-json",
+        report.to_str().expect("UTF-8 report path"),
+        "clang",
+        "format",
+    ]);
+    assert_eq!(code(&output), Some(0));
+    let records = read_report();
+    assert_eq!(records.len(), 1);
+    let record = records[0].as_object().expect("record object");
+    let mut keys = record.keys().map(String::as_str).collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, [
+        "argv",
+        "cwd",
+        "duration_ms",
+        "error",
+        "exit_code",
+        "name",
+        "signal",
+        "start_time_ms"
+    ]);
+    assert_eq!(record["name"], "clang");
+    let run_clang_format = fixture.bin().join("run-clang-format");
+    assert_eq!(record["argv"][0], *run_clang_format.to_string_lossy());
+    assert_eq!(record["exit_code"], 0);
+    assert!(record["start_time_ms"].as_u64().is_some_and(|ms| ms > 0));
+    assert!(record["duration_ms"].is_u64());
+    assert!(record["signal"].is_null());
+    assert!(record["error"].is_null());
+
+    fixture.unstub("run-clang-format");
+    let output = fixture.xtask(&[
+        "--report-json",
+        report.to_str().expect("UTF-8 report path"),
+        "clang",
+        "format",
+    ]);
+    assert_eq!(code(&output), Some(127));
+    let records = read_report();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["name"], "clang");
+    assert_eq!(records[0]["argv"][0], *run_clang_format.to_string_lossy());
+    assert!(records[0]["exit_code"].is_null());
+    assert!(
+        records[0]["error"]
+            .as_str()
+            .is_some_and(|error| error.contains("run-clang-format"))
+    );
+
+    fixture.remove("build/cxx-auto-context.json");
+    let output = fixture.xtask(&[
+        "--report-json",
+        report.to_str().expect("UTF-8 report path"),
+        "clang",
+        "format",
+    ]);
+    assert_eq!(code(&output), Some(3));
+    let records = read_report();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["name"], "clang");
+    assert_eq!(records[0]["argv"], serde_json::json!([]));
+    assert!(records[0]["exit_code"].is_null());
+    assert!(
+        records[0]["error"]
+            .as_str()
+            .is_some_and(|error| error.contains("cxx-auto-context.json"))
+    );
+}
+
+#[test]
+fn failing_prerequisite_skips_command() {
+    let fixture = Fixture::new();
+    fixture.stub("cmake", 5);
+    let output = fixture.xtask(&["clang", "tidy"]);
+    assert_eq!(code(&output), Some(5));
+    assert!(stderr(&output).contains("prerequisite `cmake build` of `xtask clang tidy` failed"));
+    assert!(!fixture.argvs().iter().any(|argv| argv.starts_with("run-clang-tidy")));
+    fixture.unstub("cmake");
+    // The real `cmake`, if installed, is found further along `PATH`.
+    if which("cmake").is_none() {
+        let output = fixture.xtask(&["clang", "tidy"]);
+        assert_eq!(code(&output), Some(127));
+        assert!(!fixture.argvs().iter().any(|argv| argv.starts_with("run-clang-tidy")));
+    }
+}
+
+#[test]
+fn invalid_arguments_skip_prerequisites() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask(&["clang", "tidy", "--bogus"]);
+    assert_eq!(code(&output), Some(2));
+    let output = fixture.xtask(&["tarpaulin", "-p"]);
+    assert_eq!(code(&output), Some(2));
+    assert_eq!(fixture.argvs(), Vec::<String>::new());
+}
+
+#[test]
+fn tarpaulin_passes_package_selection_to_build() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask(&["tarpaulin", "-p", "fixture"]);
+    assert_eq!(code(&output), Some(0));
+    let argvs = fixture.argvs();
+    assert_eq!(argvs[0], "cargo build --package fixture");
+    assert!(argvs[1].contains("tarpaulin --packages fixture"));
+}
+
+#[test]
+fn missing_tool_exits_127() {
+    let fixture = Fixture::new();
+    fixture.unstub("cmake");
+    let output = fixture.xtask_in(&fixture.root(), &["cmake", "build"]);
+    // The real `cmake`, if installed, is found further along `PATH`.
+    if which("cmake").is_none() {
+        assert_eq!(code(&output), Some(127));
+        assert!(stderr(&output).contains("tool `cmake` not found"));
+    }
+    fixture.unstub("run-clang-format");
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(127));
+    assert!(stderr(&output).contains("run-clang-format"));
+}
+
+#[test]
+fn missing_context_exits_3() {
+    let fixture = Fixture::new();
+    fixture.remove("build/cxx-auto-context.json");
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(3));
+    assert!(stderr(&output).contains("cxx-auto-context.json` required by `xtask clang format`"));
+    assert!(fixture.calls().is_empty());
+}
+
+#[test]
+fn missing_context_is_only_needed_by_clang() {
+    let fixture = Fixture::new();
+    fixture.remove("build/cxx-auto-context.json");
+    let output = fixture.xtask(&["fmt"]);
+    assert_eq!(code(&output), Some(0));
+    let output = fixture.xtask(&["test"]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs(), [
+        format!("cargo +{CHANNEL} fmt --all"),
+        String::from("cargo test --package fixture"),
+    ]);
+}
+
+#[test]
+fn missing_context_is_generated_with_auto_configure() {
+    let fixture = Fixture::new();
+    fixture.remove("build/cxx-auto-context.json");
+    fixture.stub_cmake_configure();
+    let output = fixture.xtask(&["--auto-configure", "clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    let clang_format = fixture.bin().join("clang-format");
+    assert_eq!(fixture.argvs(), [
+        String::from("cmake -G Ninja -S . -B build"),
+        format!("run-clang-format --clang-format-executable {}", clang_format.display()),
+    ]);
+}
+
+#[test]
+fn failing_auto_configure_exits_with_cmake_code() {
+    let fixture = Fixture::new();
+    fixture.remove("build/cxx-auto-context.json");
+    fixture.stub("cmake", 5);
+    let output = fixture.xtask(&["--auto-configure", "clang", "format"]);
+    assert_eq!(code(&output), Some(5));
+    assert_eq!(fixture.argvs(), ["cmake -G Ninja -S . -B build"]);
+}
+
+#[test]
+fn stale_context_is_regenerated_with_auto_configure() {
+    let fixture = Fixture::new();
+    fixture.write("CMakeLists.txt", "cmake_minimum_required(VERSION 3.20)\n");
+    fixture.age("build/cxx-auto-context.json");
+    fixture.stub_cmake_configure();
+    let output = fixture.xtask(&["--auto-configure", "clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs().len(), 2);
+    assert_eq!(fixture.argvs()[0], "cmake -G Ninja -S . -B build");
+    // The regenerated context is fresh, so the build is not configured again.
+    let output = fixture.xtask(&["--auto-configure", "clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    assert_eq!(fixture.argvs().len(), 3);
+}
+
+#[test]
+fn stale_context_is_regenerated_with_auto_configure_when_verbose() {
+    let fixture = Fixture::new();
+    fixture.write("CMakeLists.txt", "cmake_minimum_required(VERSION 3.20)\n");
+    fixture.age("build/cxx-auto-context.json");
+    fixture.stub_cmake_configure();
+    let output = fixture.xtask(&["-vv", "--auto-configure", "clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    assert!(stderr(&output).contains("[xtask] cmake context BIN_RUN_CLANG_FORMAT = "));
+    let argvs = fixture.argvs();
+    assert_eq!(argvs.len(), 2);
+    assert_eq!(argvs[0], "cmake -G Ninja -S . -B build");
+    assert!(argvs[1].starts_with("run-clang-format"));
+}
+
+#[test]
+fn stale_context_is_used_with_a_warning() {
+    let fixture = Fixture::new();
+    fixture.write("CMakeLists.txt", "cmake_minimum_required(VERSION 3.20)\n");
+    fixture.age("build/cxx-auto-context.json");
+    let output = fixture.xtask(&["clang", "format"]);
+    assert_eq!(code(&output), Some(0));
+    assert!(stderr(&output).contains("is older than `CMakeLists.txt`"));
+    assert!(fixture.argvs()[0].starts_with("run-clang-format"));
+}
+
+#[test]
+fn missing_toolchain_is_only_needed_by_nightly_commands() {
+    let fixture = Fixture::new();
+    fixture.remove("rust-toolchain.toml");
+    let output = fixture.xtask(&["build"]);
+    assert_eq!(code(&output), Some(0));
+    let output = fixture.xtask(&["clippy"]);
+    assert_eq!(code(&output), Some(3));
+    assert!(stderr(&output).contains("rust-toolchain.toml` required by `xtask clippy`"));
+    assert_eq!(fixture.argvs(), ["cargo build --package fixture"]);
+}
+
+#[test]
+fn completions_work_outside_a_workspace() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask_in(&fixture.bin(), &["completions", "bash"]);
+    assert_eq!(code(&output), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("complete -F _xtask -o default xtask"));
+}
+
+#[test]
+fn stable_toolchain_for_nightly_command_exits_4() {
+    let fixture = Fixture::new();
+    fixture.write("rust-toolchain.toml", "[toolchain]\nchannel = \"1.73.0\"\n");
+    let output = fixture.xtask(&["fmt"]);
+    assert_eq!(code(&output), Some(4));
+    assert!(stderr(&output).contains("has version 1.73.0 but a nightly channel for `xtask fmt` is required"));
+    assert_eq!(fixture.argvs(), Vec::<String>::new());
+}
+
+#[test]
+fn unknown_command_exits_2() {
+    let fixture = Fixture::new();
+    let output = fixture.xtask(&["clnag"]);
+    assert_eq!(code(&output), Some(2));
+    assert!(fixture.calls().is_empty());
+}
+
+#[test]
+fn misspelled_global_flag_is_suggested() {
+    let fixture = Fixture::new();
+    for args in [&["--dry-rn", "build"][..], &["build", "--dry-rn"], &[
+        "clang", "tidy", "--dry-rn",
+    ]] {
+        let output = fixture.xtask(args);
+        assert_eq!(code(&output), Some(2));
+        assert!(stderr(&output).contains("did you mean `--dry-run`?"));
+    }
+    assert!(fixture.calls().is_empty());
+}
+
+fn which(tool: &str) -> Option<std::path::PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(tool))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Tools run from a terminal can read from it, one after the other, rather than being stopped for reading from a
+/// background process group.
+#[cfg(target_os = "linux")]
+#[test]
+fn tools_read_from_terminal() {
+    use std::{
+        io::Write,
+        process::Stdio,
+        time::{Duration, Instant},
+    };
+
+    let fixture = Fixture::new();
+    for tool in ["cargo", "cmake"] {
+        let path = fixture.root().join(format!("{tool}.line"));
+        fixture.stub_with(
+            tool,
+            &format!("read line && printf '%s' \"$line\" > '{}'", path.display()),
+            0,
+        );
+    }
+    // `script` runs xtask with a pseudo-terminal as its controlling terminal and stdin.
+    let xtask = env!("CARGO_BIN_EXE_cxx-auto-xtask");
+    let mut script = fixture
+        .command("script")
+        .args([
+            "--quiet",
+            "--return",
+            "--command",
+            &format!("{xtask} clang tidy"),
+            "/dev/null",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("run script");
+    let mut stdin = script.stdin.take().expect("script stdin");
+    stdin.write_all(b"first\nsecond\n").expect("write to script");
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let status = loop {
+        if let Some(status) = script.try_wait().expect("wait for script") {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            script.kill().ok();
+            panic!("xtask did not finish; a tool was probably stopped reading from the terminal");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    drop(stdin);
+    assert!(status.success());
+    let line = |tool: &str| std::fs::read_to_string(fixture.root().join(format!("{tool}.line"))).expect("read line");
+    assert_eq!(line("cargo"), "first");
+    assert_eq!(line("cmake"), "second");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn stopped_tool_returns_terminal_to_shell() {
+    use std::{
+        io::{Read, Write},
+        process::Stdio,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    let fixture = Fixture::new();
+    let path = fixture.root().join("cmake.line");
+    fixture.stub_with(
+        "cmake",
+        &format!("read line && printf '%s' \"$line\" > '{}'", path.display()),
+        0,
+    );
+    // `script` runs an interactive shell with job control, which runs xtask as a job of its own.
+    let mut script = fixture
+        .command("script")
+        .args([
+            "--quiet",
+            "--return",
+            "--command",
+            "bash --norc --noprofile -i",
+            "/dev/null",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("run script");
+    let mut stdin = script.stdin.take().expect("script stdin");
+    let mut stdout = script.stdout.take().expect("script stdout");
+    let output = Arc::new(Mutex::new(String::new()));
+    let reader = std::thread::spawn({
+        let output = output.clone();
+        move || {
+            let mut buffer = [0; 1024];
+            while let Ok(read @ 1 ..) = stdout.read(&mut buffer) {
+                output
+                    .lock()
+                    .expect("output lock")
+                    .push_str(&String::from_utf8_lossy(&buffer[.. read]));
+            }
+        }
+    });
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut wait_for = |done: &dyn Fn() -> bool, what: &str| {
+        while !done() {
+            if Instant::now() >= deadline {
+                script.kill().ok();
+                panic!(
+                    "timed out waiting for {what}; output:\n{}",
+                    output.lock().expect("output lock")
+                );
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    };
+    // The shell prints the results of arithmetic expansions, which do not occur in the echoed input.
+    let printed = |text: &str| output.lock().expect("output lock").contains(text);
+
+    let xtask = env!("CARGO_BIN_EXE_cxx-auto-xtask");
+    writeln!(stdin, "{xtask} cmake build").expect("write to script");
+    wait_for(&|| !fixture.calls().is_empty(), "the cmake stub to start");
+    stdin.write_all(b"\x1a").expect("write to script");
+    wait_for(&|| printed("Stopped"), "the shell to report the job stopped");
+    writeln!(stdin, "echo $((6 * 7))-stopped").expect("write to script");
+    wait_for(&|| printed("42-stopped"), "the shell to regain the terminal");
+    writeln!(stdin, "fg").expect("write to script");
+    // Leave the shell time to hand the terminal back to xtask before the stub's input arrives.
+    std::thread::sleep(Duration::from_millis(500));
+    writeln!(stdin, "resumed").expect("write to script");
+    writeln!(stdin, "echo $((6 * 8))-finished-$?").expect("write to script");
+    wait_for(&|| printed("48-finished-0"), "xtask to finish");
+    writeln!(stdin, "exit").expect("write to script");
+    drop(stdin);
+    script.wait().expect("wait for script");
+    reader.join().ok();
+    assert_eq!(std::fs::read_to_string(&path).expect("read line"), "resumed");
+}